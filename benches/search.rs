@@ -0,0 +1,117 @@
+//! Compares searching a document with every pattern in turn against searching it with the
+//! `RegexSet` prefilter. Run with `cargo bench --bench search`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use regex::{Regex, RegexBuilder};
+use sift::file_handler::search_content;
+use sift::patterns::Patterns;
+use sift::rules::Rule;
+use std::collections::HashSet;
+
+/// Number of patterns in a large profile.
+const PATTERN_COUNT: usize = 150;
+
+/// A profile of identifier, keyword and PII patterns, few of which match any one document.
+fn keywords() -> Vec<String> {
+    let mut keywords = vec![
+        r"\d\d\d-\d\d-\d\d\d\d".to_string(),
+        r"[a-z0-9.]+@example\.com".to_string(),
+        r"\b(?:\d[ -]?){15}\d\b".to_string(),
+        r"AKIA[0-9A-Z]{16}".to_string(),
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----".to_string(),
+    ];
+    let mut i = 0;
+    while keywords.len() < PATTERN_COUNT {
+        keywords.push(match i % 3 {
+            0 => format!(r"project-{}-\d{{4}}", i),
+            1 => format!(r"codename{}\s+\w+", i),
+            _ => format!(r"\bcase\s+#?{}\d{{3}}\b", i),
+        });
+        i += 1;
+    }
+    keywords
+}
+
+/// About 1 MB of prose with a handful of findings.
+fn document() -> String {
+    let paragraph = "The quarterly review covered staffing, the data retention schedule and \
+        the migration of archived reports to the new storage tier. Action items were \
+        assigned to each team lead and will be revisited at the next meeting.\n";
+    let mut text = paragraph.repeat(4500);
+    text.push_str("Contact jane.doe@example.com about 123-45-6789 and project-7-2024.\n");
+    text
+}
+
+/// Searching as before the prefilter: every pattern runs over the whole document, and
+/// collects its values the same way `search_content` does.
+fn search_each(content: &str, patterns: &[Regex]) -> (Vec<String>, Vec<(usize, usize)>) {
+    let mut values = Vec::new();
+    let mut counts = Vec::new();
+    let mut seen = HashSet::new();
+    let mut push = |value: &str, values: &mut Vec<String>| {
+        if !value.is_empty() && seen.insert(value.to_string()) {
+            values.push(value.to_string());
+        }
+    };
+
+    for (index, pattern) in patterns.iter().enumerate() {
+        let mut count = 0;
+        if pattern.captures_len() == 1 {
+            for found in pattern.find_iter(content).filter(|m| !m.is_empty()) {
+                count += 1;
+                push(found.as_str(), &mut values);
+            }
+        } else {
+            for captures in pattern.captures_iter(content) {
+                if captures.get(0).is_none_or(|m| m.is_empty()) {
+                    continue;
+                }
+                count += 1;
+                for group in captures.iter().flatten() {
+                    push(group.as_str(), &mut values);
+                }
+            }
+        }
+        if count > 0 {
+            counts.push((index, count));
+        }
+    }
+    (values, counts)
+}
+
+fn search(c: &mut Criterion) {
+    let regexes: Vec<Regex> = keywords()
+        .iter()
+        .map(|pattern| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .unwrap()
+        })
+        .collect();
+    let rules = keywords()
+        .into_iter()
+        .enumerate()
+        .map(|(i, pattern)| Rule::from_pattern(format!("rule-{}", i + 1), pattern))
+        .collect();
+    let patterns = Patterns::new(rules, Vec::new(), false).unwrap();
+    let content = document();
+
+    let mut group = c.benchmark_group("search");
+    group.throughput(Throughput::Bytes(content.len() as u64));
+    group.sample_size(10);
+    group.bench_with_input(
+        BenchmarkId::new("each pattern", PATTERN_COUNT),
+        &content,
+        |b, content| b.iter(|| search_each(content, &regexes)),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("regex set prefilter", PATTERN_COUNT),
+        &content,
+        |b, content| b.iter(|| search_content(content, &patterns)),
+    );
+    group.finish();
+}
+
+criterion_group!(benches, search);
+criterion_main!(benches);
@@ -11,11 +11,19 @@ pub struct Args {
     pub add_patterns: Option<Vec<String>>,
     pub remove_patterns: Option<Vec<String>>,
     pub display_patterns: bool,
+    pub enable_packs: Option<Vec<String>>,
+    pub disable_packs: Option<Vec<String>>,
+    pub list_packs: bool,
+    pub add_key_patterns: Option<Vec<String>>,
+    pub remove_key_patterns: Option<Vec<String>>,
+    pub set_extensions: Option<Vec<String>>,
+    pub remove_extensions: Option<Vec<String>>,
     pub output_directory: Option<String>,
     pub print_settings: bool,
     pub reset_settings: bool,
     pub case_sensitive: bool,
     pub pattern_file: Option<String>,
+    pub rule_file: Option<String>,
     pub config_file: String,
 }
 
@@ -69,7 +77,7 @@ pub fn get_args() -> Result<Args, Box<dyn Error>> {
             Arg::new("remove_patterns")
                 .short('A')
                 // .value_name("keywords_to_remove")
-                .help("removes patterns from config file")
+                .help("removes patterns from config file, by pattern or rule id")
                 .action(ArgAction::Append)
                 .num_args(1..),
         )
@@ -77,9 +85,58 @@ pub fn get_args() -> Result<Args, Box<dyn Error>> {
             Arg::new("display_patterns")
                 .short('k')
                 // .value_name("display_keywords")
-                .help("displays decrypted keywords list")
+                .help("displays decrypted rules and key rules")
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("enable_packs")
+                .short('p')
+                .action(ArgAction::Append)
+                .num_args(1..)
+                .help("enables built-in rule pack(s): pii-us, pii-eu, pci, phi, secrets"),
+        )
+        .arg(
+            Arg::new("disable_packs")
+                .short('P')
+                .help("disables built-in rule pack(s)")
+                .action(ArgAction::Append)
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("list_packs")
+                .short('t')
+                .help("lists the built-in rule packs and their rules")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("add_key_patterns")
+                .short('n')
+                .action(ArgAction::Append)
+                .num_args(1..)
+                .help("adds key name pattern(s) whose values are reported in JSON, YAML and TOML"),
+        )
+        .arg(
+            Arg::new("remove_key_patterns")
+                .short('N')
+                .help("removes key rules from config file by id or pattern")
+                .action(ArgAction::Append)
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("set_extensions")
+                .short('e')
+                .value_name("extension=extractor")
+                .action(ArgAction::Append)
+                .num_args(1..)
+                .help("maps file extension(s) to an extractor, e.g. log=text"),
+        )
+        .arg(
+            Arg::new("remove_extensions")
+                .short('E')
+                .help("removes file extension(s) from the extractor mapping")
+                .action(ArgAction::Append)
+                .num_args(1..),
+        )
         .arg(
             Arg::new("set_output_directory")
                 .short('o')
@@ -112,6 +169,12 @@ pub fn get_args() -> Result<Args, Box<dyn Error>> {
                 .help("provide a path to a text file containing patterns (comma separated)")
                 .default_value(None),
         )
+        .arg(
+            Arg::new("rule_file")
+                .short('u')
+                .value_name("rule_file")
+                .help("imports rules from a TOML file of [[rules]] and [[key_rules]] tables, replacing rules with the same id"),
+        )
         .arg(
             Arg::new("config_name")
                 .short('c')
@@ -124,34 +187,46 @@ pub fn get_args() -> Result<Args, Box<dyn Error>> {
         scan: matches.get_flag("scan"),
         full_scan: matches.get_flag("full_scan"),
         verbose: matches.get_flag("verbose"),
-        roots: match matches.get_many::<String>("root") {
-            Some(c) => Some(c.into_iter().map(|v| v.clone()).collect()),
-            None => None,
-        },
-        remove_roots: match matches.get_many::<String>("remove_root") {
-            Some(c) => Some(c.into_iter().map(|v| v.clone()).collect()),
-            None => None,
-        },
-        add_patterns: match matches.get_many::<String>("add_patterns") {
-            Some(c) => Some(c.into_iter().map(|v| v.clone()).collect()),
-            None => None,
-        },
-        remove_patterns: match matches.get_many::<String>("remove_patterns") {
-            Some(c) => Some(c.into_iter().map(|v| v.clone()).collect()),
-            None => None,
-        },
+        roots: matches
+            .get_many::<String>("root")
+            .map(|c| c.into_iter().cloned().collect()),
+        remove_roots: matches
+            .get_many::<String>("remove_root")
+            .map(|c| c.into_iter().cloned().collect()),
+        add_patterns: matches
+            .get_many::<String>("add_patterns")
+            .map(|c| c.into_iter().cloned().collect()),
+        remove_patterns: matches
+            .get_many::<String>("remove_patterns")
+            .map(|c| c.into_iter().cloned().collect()),
         display_patterns: matches.get_flag("display_patterns"),
-        output_directory: match matches.get_one::<String>("set_output_directory") {
-            Some(c) => Some(c.clone()),
-            None => None,
-        },
+        enable_packs: matches
+            .get_many::<String>("enable_packs")
+            .map(|c| c.into_iter().cloned().collect()),
+        disable_packs: matches
+            .get_many::<String>("disable_packs")
+            .map(|c| c.into_iter().cloned().collect()),
+        list_packs: matches.get_flag("list_packs"),
+        add_key_patterns: matches
+            .get_many::<String>("add_key_patterns")
+            .map(|c| c.into_iter().cloned().collect()),
+        remove_key_patterns: matches
+            .get_many::<String>("remove_key_patterns")
+            .map(|c| c.into_iter().cloned().collect()),
+        set_extensions: matches
+            .get_many::<String>("set_extensions")
+            .map(|c| c.into_iter().cloned().collect()),
+        remove_extensions: matches
+            .get_many::<String>("remove_extensions")
+            .map(|c| c.into_iter().cloned().collect()),
+        output_directory: matches.get_one::<String>("set_output_directory").cloned(),
         print_settings: matches.get_flag("print_settings"),
         reset_settings: matches.get_flag("reset_config"),
         case_sensitive: matches.get_flag("case-sensitive"),
-        pattern_file: match matches.get_one::<String>("pattern_file") {
-            Some(pf) => Some(pf.to_string()),
-            None => None,
-        },
+        pattern_file: matches
+            .get_one::<String>("pattern_file")
+            .map(|pf| pf.to_string()),
+        rule_file: matches.get_one::<String>("rule_file").cloned(),
         config_file: matches
             .get_one::<String>("config_name")
             .unwrap()
@@ -0,0 +1,462 @@
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use std::io::{self, Cursor, Read, Seek};
+use std::path::Path;
+use xz2::read::XzDecoder;
+
+use crate::file_handler::{
+    is_scannable, scan_document, Document, ExtractSettings, Format, ScanContext,
+};
+use crate::sniff;
+
+/// Unpacks each member of a zip archive and scans it.
+pub fn scan_zip(doc: &Document, ctx: &mut ScanContext) {
+    scan_zip_members(doc, ctx, |name| Some(name.to_string()));
+}
+
+/// Scans the packages and OLE objects embedded in an OOXML document, which are stored as
+/// `word/embeddings/oleObject1.bin` and reported as `report.docx!/embeddings/oleObject1.bin`.
+pub fn scan_embeddings(doc: &Document, ctx: &mut ScanContext) {
+    let embedded = |name: &str| {
+        let (_, rest) = name.split_once('/')?;
+        match rest.starts_with("embeddings/") {
+            true => Some(rest.to_string()),
+            false => None,
+        }
+    };
+    let has_embeddings = match zip::ZipArchive::new(Cursor::new(doc.data)) {
+        Ok(archive) => archive.file_names().any(|n| embedded(n).is_some()),
+        Err(_) => false,
+    };
+    if has_embeddings {
+        scan_zip_members(doc, ctx, embedded);
+    }
+}
+
+/// Unpacks and scans the zip members that `select` gives a reported name.
+fn scan_zip_members<F>(doc: &Document, ctx: &mut ScanContext, select: F)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if !can_open(doc, ctx) {
+        return;
+    }
+    let mut archive = match zip::ZipArchive::new(Cursor::new(doc.data)) {
+        Ok(z) => z,
+        Err(e) => {
+            log::error!("failed to open zip archive {}: {}", doc.path, e);
+            return;
+        }
+    };
+
+    for i in 0..archive.len() {
+        let mut member = match archive.by_index(i) {
+            Ok(m) => m,
+            Err(e) => {
+                log::error!("failed to read zip member in {}: {}", doc.path, e);
+                continue;
+            }
+        };
+        if member.is_dir() {
+            continue;
+        }
+        let name = match select(member.name()) {
+            Some(n) => n,
+            None => continue,
+        };
+        let hint = Format::from_name(&name, &ctx.settings.extensions);
+
+        let ratio_limit = member
+            .compressed_size()
+            .saturating_mul(ctx.settings.max_container_ratio);
+        if member.size() > ratio_limit {
+            log::warn!(
+                "skipped {}!/{}: compression ratio exceeds {}",
+                doc.path,
+                name,
+                ctx.settings.max_container_ratio
+            );
+            continue;
+        }
+        if member.size() > ctx.remaining_bytes {
+            log::warn!(
+                "stopped unpacking {}: container size limit of {} bytes reached",
+                doc.path,
+                ctx.settings.max_container_bytes
+            );
+            return;
+        }
+
+        let head = match read_head(&mut member, &name, hint, ctx.settings) {
+            Ok(Some(h)) => h,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("failed to unpack {}!/{}: {}", doc.path, name, e);
+                continue;
+            }
+        };
+        // The declared size can't be trusted, so the read itself is capped as well.
+        let limit = ratio_limit.min(ctx.remaining_bytes);
+        let data = match read_limited(member, limit.saturating_sub(head.len() as u64)) {
+            Ok(Some(d)) => [head, d].concat(),
+            Ok(None) => {
+                log::warn!(
+                    "stopped unpacking {}: {} inflates past its declared size",
+                    doc.path,
+                    name
+                );
+                return;
+            }
+            Err(e) => {
+                log::error!("failed to unpack {}!/{}: {}", doc.path, name, e);
+                continue;
+            }
+        };
+
+        scan_member(doc, &name, hint, &data, ctx);
+    }
+}
+
+/// Unpacks each file in a tar archive and scans it.
+pub fn scan_tar(doc: &Document, ctx: &mut ScanContext) {
+    if !can_open(doc, ctx) {
+        return;
+    }
+    scan_tar_entries(doc, doc.data, ctx);
+}
+
+/// Decompresses a gzipped tar archive and scans each file in it.
+pub fn scan_tar_gz(doc: &Document, ctx: &mut ScanContext) {
+    if !can_open(doc, ctx) {
+        return;
+    }
+
+    let ratio_limit = (doc.data.len() as u64).saturating_mul(ctx.settings.max_container_ratio);
+    let data = match read_limited(
+        GzDecoder::new(doc.data),
+        ratio_limit.min(ctx.remaining_bytes),
+    ) {
+        Ok(Some(d)) => d,
+        Ok(None) => {
+            log::warn!(
+                "skipped {}: decompressed size exceeds the container limits",
+                doc.path
+            );
+            return;
+        }
+        Err(e) => {
+            log::error!("failed to decompress {}: {}", doc.path, e);
+            return;
+        }
+    };
+
+    // Only the unpacked files count against the container budget, in `scan_member`.
+    scan_tar_entries(doc, &data, ctx);
+}
+
+/// File name suffixes of single-file compression formats.
+const COMPRESSION_SUFFIXES: [&str; 4] = [".gz", ".bz2", ".xz", ".zst"];
+
+/// Decompresses a gzip, bzip2, xz or zstd stream and scans the result as the file named
+/// without the compression suffix, so `export.csv.gz` is read as csv. The stream is
+/// decompressed up to `max_decompressed_bytes` and skipped when it holds more.
+pub fn scan_compressed(doc: &Document, ctx: &mut ScanContext) {
+    if !can_decompress(doc, ctx) {
+        return;
+    }
+
+    let decoder: Box<dyn Read> = match doc.format {
+        Format::Gzip => Box::new(GzDecoder::new(doc.data)),
+        Format::Bzip2 => Box::new(BzDecoder::new(doc.data)),
+        Format::Xz => Box::new(XzDecoder::new(doc.data)),
+        Format::Zstd => match zstd::Decoder::new(doc.data) {
+            Ok(d) => Box::new(d),
+            Err(e) => {
+                log::error!("failed to decompress {}: {}", doc.path, e);
+                return;
+            }
+        },
+        _ => return,
+    };
+    let limit = ctx.settings.max_decompressed_bytes.min(ctx.remaining_bytes);
+    let data = match read_limited(decoder, limit) {
+        Ok(Some(d)) => d,
+        Ok(None) => {
+            log::warn!(
+                "skipped {}: decompressed size exceeds {} bytes",
+                doc.path,
+                limit
+            );
+            return;
+        }
+        Err(e) => {
+            log::error!("failed to decompress {}: {}", doc.path, e);
+            return;
+        }
+    };
+
+    let inner_name = COMPRESSION_SUFFIXES
+        .iter()
+        .find_map(|suffix| {
+            let len = doc.name.len().checked_sub(suffix.len())?;
+            match doc.name.get(len..)?.eq_ignore_ascii_case(suffix) {
+                true => Some(&doc.name[..len]),
+                false => None,
+            }
+        })
+        .unwrap_or(&doc.name);
+    let hint = Format::from_name(inner_name, &ctx.settings.extensions);
+    match Document::detect(
+        doc.path.clone(),
+        doc.name.clone(),
+        hint,
+        &data,
+        doc.depth + 1,
+        ctx.settings,
+    ) {
+        Some(inner) => {
+            // Tar members are slices of the decompressed data and count against the
+            // container budget on their own, in `scan_member`.
+            if inner.format != Format::Tar {
+                ctx.remaining_bytes -= data.len() as u64;
+            }
+            scan_document(&inner, ctx)
+        }
+        None => {
+            if ctx.settings.verbose {
+                println!("Did not scan: {}", doc.path)
+            }
+        }
+    }
+}
+
+fn scan_tar_entries(doc: &Document, data: &[u8], ctx: &mut ScanContext) {
+    let mut archive = tar::Archive::new(data);
+    let entries = match archive.entries() {
+        Ok(e) => e,
+        Err(e) => {
+            log::error!("failed to open tar archive {}: {}", doc.path, e);
+            return;
+        }
+    };
+
+    for entry in entries {
+        let mut entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                log::error!("failed to read tar entry in {}: {}", doc.path, e);
+                return;
+            }
+        };
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = match entry.path() {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(_) => continue,
+        };
+        let hint = Format::from_name(&name, &ctx.settings.extensions);
+        if entry.size() > ctx.remaining_bytes {
+            log::warn!(
+                "stopped unpacking {}: container size limit of {} bytes reached",
+                doc.path,
+                ctx.settings.max_container_bytes
+            );
+            return;
+        }
+
+        let head = match read_head(&mut entry, &name, hint, ctx.settings) {
+            Ok(Some(h)) => h,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("failed to unpack {}!/{}: {}", doc.path, name, e);
+                continue;
+            }
+        };
+        let limit = ctx.remaining_bytes.saturating_sub(head.len() as u64);
+        let data = match read_limited(entry, limit) {
+            Ok(Some(d)) => [head, d].concat(),
+            Ok(None) => {
+                log::warn!(
+                    "stopped unpacking {}: container size limit of {} bytes reached",
+                    doc.path,
+                    ctx.settings.max_container_bytes
+                );
+                return;
+            }
+            Err(e) => {
+                log::error!("failed to unpack {}!/{}: {}", doc.path, name, e);
+                continue;
+            }
+        };
+
+        scan_member(doc, &name, hint, &data, ctx);
+    }
+}
+
+/// Checks the nesting depth before a container is opened.
+pub fn can_open(doc: &Document, ctx: &ScanContext) -> bool {
+    if doc.depth >= ctx.settings.max_container_depth {
+        log::warn!(
+            "did not open {}: container nesting deeper than {}",
+            doc.path,
+            ctx.settings.max_container_depth
+        );
+        return false;
+    }
+    true
+}
+
+/// Checks the nesting depth before a single compressed file is decompressed. A compressed
+/// file is read even where containers are no longer opened, so a plain `.log.gz` is
+/// scanned with container scanning disabled, while compressed streams can't nest forever.
+fn can_decompress(doc: &Document, ctx: &ScanContext) -> bool {
+    if doc.depth > ctx.settings.max_container_depth {
+        log::warn!(
+            "did not decompress {}: nesting deeper than {}",
+            doc.path,
+            ctx.settings.max_container_depth
+        );
+        return false;
+    }
+    true
+}
+
+/// Scans a document unpacked from `parent`, reported as `{parent}!/{name}`.
+pub fn scan_member(
+    parent: &Document,
+    name: &str,
+    hint: Option<Format>,
+    data: &[u8],
+    ctx: &mut ScanContext,
+) {
+    ctx.remaining_bytes = ctx.remaining_bytes.saturating_sub(data.len() as u64);
+    let filename = match Path::new(name).file_name() {
+        Some(f) => f.to_string_lossy().to_string(),
+        None => name.to_string(),
+    };
+    let path = format!("{}!/{}", parent.path, name.trim_start_matches('/'));
+    match Document::detect(path, filename, hint, data, parent.depth + 1, ctx.settings) {
+        Some(member) => scan_document(&member, ctx),
+        None => {
+            if ctx.settings.verbose {
+                println!("Did not scan: {}!/{}", parent.path, name)
+            }
+        }
+    }
+}
+
+/// Reads the first bytes of a member whose name doesn't suggest a format, returning `None`
+/// when nothing can extract it. Members with a known name return an empty head.
+fn read_head<R: Read>(
+    reader: &mut R,
+    name: &str,
+    hint: Option<Format>,
+    settings: &ExtractSettings,
+) -> io::Result<Option<Vec<u8>>> {
+    let mut head = Vec::new();
+    if hint.is_some() {
+        return Ok(Some(head));
+    }
+    reader.take(sniff::HEAD_LEN as u64).read_to_end(&mut head)?;
+    match is_scannable(name, hint, &head, settings) {
+        true => Ok(Some(head)),
+        false => Ok(None),
+    }
+}
+
+/// How far a part of a zip based document may inflate when an extractor reads it in full.
+#[derive(Clone, Copy)]
+pub struct PartLimits {
+    /// Multiple of the part's compressed size, as `max_container_ratio`.
+    pub ratio: u64,
+    /// Size in bytes, as the container budget left.
+    pub bytes: u64,
+}
+
+impl PartLimits {
+    pub fn new(ctx: &ScanContext) -> PartLimits {
+        PartLimits {
+            ratio: ctx.settings.max_container_ratio,
+            bytes: ctx.remaining_bytes,
+        }
+    }
+
+    fn limit(&self, compressed_size: u64) -> u64 {
+        compressed_size.saturating_mul(self.ratio).min(self.bytes)
+    }
+}
+
+/// Reads a part of a zip based document, returning `None` when it is missing, unreadable or
+/// inflates past `limits`.
+pub fn read_part<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+    limits: PartLimits,
+) -> Option<Vec<u8>> {
+    let part = archive.by_name(name).ok()?;
+    let limit = limits.limit(part.compressed_size());
+    match read_limited(part, limit) {
+        Ok(Some(data)) => Some(data),
+        Ok(None) => {
+            log::warn!("skipped part {}: inflates past {} bytes", name, limit);
+            None
+        }
+        Err(e) => {
+            log::error!("failed to read part {}: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Inflates every member of a zip archive without keeping it, so a document can be handed
+/// to a library that reads its parts unchecked. Returns false when a member inflates past
+/// `limits`, or all of them together past `limits.bytes`.
+pub fn check_parts(data: &[u8], limits: PartLimits) -> bool {
+    let mut archive = match zip::ZipArchive::new(Cursor::new(data)) {
+        Ok(z) => z,
+        // Not a zip, so the library reads it some other way.
+        Err(_) => return true,
+    };
+    let mut total: u64 = 0;
+    for i in 0..archive.len() {
+        let part = match archive.by_index(i) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+        let name = part.name().to_string();
+        let limit = limits.limit(part.compressed_size());
+        match io::copy(&mut part.take(limit.saturating_add(1)), &mut io::sink()) {
+            Ok(size) if size > limit => {
+                log::warn!(
+                    "skipped document: part {} inflates past {} bytes",
+                    name,
+                    limit
+                );
+                return false;
+            }
+            Ok(size) => total = total.saturating_add(size),
+            Err(_) => continue,
+        }
+        if total > limits.bytes {
+            log::warn!(
+                "skipped document: parts inflate past {} bytes",
+                limits.bytes
+            );
+            return false;
+        }
+    }
+    true
+}
+
+/// Reads at most `limit` bytes, returning `None` when the source holds more than that.
+pub fn read_limited<R: Read>(reader: R, limit: u64) -> io::Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > limit {
+        return Ok(None);
+    }
+    Ok(Some(data))
+}
@@ -3,7 +3,7 @@ use crossbeam::channel::Receiver;
 use csv::{Writer, WriterBuilder};
 use std::collections::VecDeque;
 use std::error::Error;
-use std::thread;
+use std::thread::{self, JoinHandle};
 use std::{
     fs::{self, File},
     path::{Path, PathBuf},
@@ -19,7 +19,14 @@ use crate::sift::ScanMessage::{Msg, END};
 /// * 'rx' - crossbeam receiver that receives thread_message enum.
 /// * "root" - the starting point for the scan.
 /// * "output_path" - the designated output directory for the csv files.
-pub fn writer(output_path: PathBuf, root: &String, rx: Receiver<ScanMessage>, max_lines: u16) {
+///
+/// Returns the handle of the writer thread, which finishes once END is received.
+pub fn writer(
+    output_path: PathBuf,
+    root: &str,
+    rx: Receiver<ScanMessage>,
+    max_lines: u16,
+) -> JoinHandle<()> {
     let mut written_lines: u16 = 0;
     let mut file_suffix: u32 = 1;
 
@@ -61,9 +68,8 @@ pub fn writer(output_path: PathBuf, root: &String, rx: Receiver<ScanMessage>, ma
             .unwrap();
             written_lines = 0;
         }
-        match rx.try_recv() {
-            Ok(m) => queue.push_back(m),
-            Err(_) => (),
+        if let Ok(m) = rx.try_recv() {
+            queue.push_back(m)
         }
         if !queue.is_empty() {
             match queue.pop_front().unwrap() {
@@ -77,23 +83,18 @@ pub fn writer(output_path: PathBuf, root: &String, rx: Receiver<ScanMessage>, ma
                 }
             }
         }
-    });
+    })
 }
 
-fn build_writer(file_path: &PathBuf) -> Result<Writer<File>, Box<dyn Error>> {
+fn build_writer(file_path: &Path) -> Result<Writer<File>, Box<dyn Error>> {
     let writer = WriterBuilder::new()
         .has_headers(true)
         .from_path(file_path)?;
     Ok(writer)
 }
 
-fn update_filename(
-    root: &String,
-    timestamp: &String,
-    file_path: &PathBuf,
-    file_suffix: u32,
-) -> PathBuf {
-    let mut updated_filename = file_path.clone();
+fn update_filename(root: &str, timestamp: &str, file_path: &Path, file_suffix: u32) -> PathBuf {
+    let mut updated_filename = file_path.to_path_buf();
     updated_filename.push(format!("{root}_{timestamp}_{file_suffix}.csv"));
     updated_filename
 }
@@ -0,0 +1,228 @@
+use csv::ReaderBuilder;
+
+use crate::file_handler::{
+    record_matches, record_segments, search_content, Document, FileFindings, Finding, Matches,
+    ScanContext, Segment,
+};
+
+/// Delimiters tried when sniffing, in order of preference on a tie.
+const DELIMITERS: [u8; 4] = [b',', b'\t', b';', b'|'];
+/// Number of lines inspected to pick the delimiter.
+const SNIFF_LINES: usize = 20;
+/// Number of matching cells reported with their row. Later matches are added up per column,
+/// so a file with millions of matching rows doesn't keep a result for each of them.
+const MAX_LOCATED_CELLS: usize = 10_000;
+
+/// Scans CSV or TSV text cell by cell, locating findings as `row 18234, column "ssn"`
+/// with the header row naming the columns. Rows are numbered as in a spreadsheet, so the
+/// header is row 1. Past the first `MAX_LOCATED_CELLS` matching cells, matches are
+/// reported per column instead.
+///
+/// When `column_threshold` is set, the first `column_sample_rows` rows are also used to flag
+/// every column where at least that percentage of non-empty cells match the same rule.
+pub fn scan(doc: &Document, text: &str, ctx: &mut ScanContext) {
+    let delimiter = sniff_delimiter(text);
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut records = reader.records();
+
+    let header: Vec<String> = match records.next() {
+        Some(Ok(record)) => record.iter().map(str::to_string).collect(),
+        Some(Err(e)) => {
+            log::error!("failed to read {}: {}", doc.path, e);
+            return;
+        }
+        None => return,
+    };
+    // The header can hold findings too, such as a column named after a person.
+    let segments = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| Segment {
+            location: Some(format!("row 1, column {}", i + 1)),
+            text: name.clone(),
+        })
+        .collect();
+    record_segments(doc, segments, ctx);
+
+    let sampling = ctx.settings.column_threshold > 0;
+    let mut samples: Vec<ColumnSample> = Vec::new();
+    let mut located = 0;
+    let mut totals: Vec<Matches> = Vec::new();
+
+    for (i, record) in records.enumerate() {
+        let row = i + 2;
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                log::error!("failed to read {} row {}: {}", doc.path, row, e);
+                continue;
+            }
+        };
+        let sampled = sampling && i < ctx.settings.column_sample_rows;
+        if sampled && samples.len() < record.len() {
+            samples.resize_with(record.len(), ColumnSample::default);
+        }
+
+        for (column, cell) in record.iter().enumerate() {
+            if cell.trim().is_empty() {
+                continue;
+            }
+            let matches = search_content(cell, ctx.patterns);
+            if sampled {
+                samples[column].add(&matches);
+            }
+            if matches.counts.is_empty() || located < MAX_LOCATED_CELLS {
+                located += usize::from(!matches.counts.is_empty());
+                let location = format!("row {}, {}", row, column_name(&header, column));
+                record_matches(doc, Some(location), matches, ctx);
+            } else {
+                if totals.len() <= column {
+                    totals.resize_with(column + 1, Matches::default);
+                }
+                add_matches(
+                    &mut totals[column],
+                    matches,
+                    ctx.settings.max_distinct_values,
+                );
+            }
+        }
+    }
+
+    let mut notes = doc.notes.clone();
+    notes.push(format!(
+        "matches after the first {} located cells, counted for the column",
+        MAX_LOCATED_CELLS
+    ));
+    let column_doc = Document {
+        path: doc.path.clone(),
+        name: doc.name.clone(),
+        notes,
+        ..*doc
+    };
+    for (column, total) in totals.into_iter().enumerate() {
+        record_matches(&column_doc, Some(column_name(&header, column)), total, ctx);
+    }
+
+    let threshold = ctx.settings.column_threshold as usize;
+    for (column, sample) in samples.into_iter().enumerate() {
+        // Each rule has to reach the threshold on its own, so a column that is partly
+        // emails and partly phone numbers isn't flagged for either.
+        let flagged: Vec<(usize, usize)> = sample
+            .matching
+            .into_iter()
+            .filter(|(_, matching)| matching * 100 >= sample.cells * threshold)
+            .collect();
+        if flagged.is_empty() {
+            continue;
+        }
+        let mut notes = doc.notes.clone();
+        for (rule, matching) in &flagged {
+            notes.push(format!(
+                "{} of {} sampled cells match {}",
+                matching,
+                sample.cells,
+                ctx.patterns.rule(*rule).label()
+            ));
+        }
+        let findings = sample
+            .findings
+            .into_iter()
+            .filter(|f| flagged.iter().any(|(rule, _)| f.rule == *rule))
+            .collect();
+        ctx.results.push(FileFindings {
+            findings,
+            match_counts: Vec::new(),
+            filename: doc.name.clone(),
+            path: doc.path.clone(),
+            location: Some(column_name(&header, column)),
+            notes,
+        });
+    }
+}
+
+/// Cells of one column seen while sampling.
+#[derive(Default)]
+struct ColumnSample {
+    cells: usize,
+    /// Number of cells each rule matched, by index in the rule list.
+    matching: Vec<(usize, usize)>,
+    findings: Vec<Finding>,
+}
+
+impl ColumnSample {
+    /// Counts a non-empty cell and what was found in it.
+    fn add(&mut self, matches: &Matches) {
+        self.cells += 1;
+        for (rule, _) in &matches.counts {
+            match self.matching.iter_mut().find(|(r, _)| r == rule) {
+                Some((_, matching)) => *matching += 1,
+                None => self.matching.push((*rule, 1)),
+            }
+        }
+        for finding in &matches.values {
+            if !self.findings.contains(finding) {
+                self.findings.push(finding.clone());
+            }
+        }
+    }
+}
+
+/// Adds the matches of a cell to its column's, keeping at most `max_values` distinct values
+/// as only those are reported.
+fn add_matches(total: &mut Matches, matches: Matches, max_values: usize) {
+    for finding in matches.values {
+        if total.values.len() < max_values && !total.values.contains(&finding) {
+            total.values.push(finding);
+        }
+    }
+    for (counts, added) in [
+        (&mut total.counts, matches.counts),
+        (&mut total.rejected, matches.rejected),
+    ] {
+        for (rule, count) in added {
+            match counts.iter_mut().find(|(r, _)| *r == rule) {
+                Some((_, total)) => *total += count,
+                None => counts.push((rule, count)),
+            }
+        }
+    }
+}
+
+/// Names a column by its header, or by its number when the header cell is blank.
+fn column_name(header: &[String], column: usize) -> String {
+    match header.get(column).map(|h| h.trim()) {
+        Some(name) if !name.is_empty() => format!("column \"{}\"", name),
+        _ => format!("column {}", column + 1),
+    }
+}
+
+/// Picks the delimiter that appears the same, non-zero number of times on most of the
+/// first lines, then the one splitting the header into the most columns. Falls back to a
+/// comma.
+fn sniff_delimiter(text: &str) -> u8 {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+    let mut best = (b',', (0, 0));
+    for delimiter in DELIMITERS {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|l| l.bytes().filter(|b| *b == delimiter).count())
+            .collect();
+        let first = match counts.first() {
+            Some(c) if *c > 0 => *c,
+            _ => continue,
+        };
+        let consistent = counts.iter().filter(|c| **c == first).count();
+        if (consistent, first) > best.1 {
+            best = (delimiter, (consistent, first));
+        }
+    }
+    best.0
+}
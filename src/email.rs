@@ -0,0 +1,174 @@
+use mail_parser::mailbox::mbox::MessageIterator;
+use mail_parser::{HeaderValue, Message, MessageParser, MimeHeaders, PartType};
+
+use crate::container;
+use crate::file_handler::{record_segments, scan_document, Document, Format, ScanContext, Segment};
+use crate::markup::html_to_text;
+
+/// Scans an RFC 5322 message, as found in `.eml` files and Maildir folders.
+pub fn scan_email(doc: &Document, ctx: &mut ScanContext) {
+    let message = match MessageParser::default().parse(doc.data) {
+        Some(m) => m,
+        None => {
+            log::error!("failed to parse email {}", doc.path);
+            return;
+        }
+    };
+    scan_message(doc, &message, "!/", ctx);
+}
+
+/// Splits an mbox mailbox into its messages and scans each one as `inbox.mbox!/message 3`.
+pub fn scan_mbox(doc: &Document, ctx: &mut ScanContext) {
+    for (i, entry) in MessageIterator::new(doc.data).enumerate() {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(_) => {
+                log::error!("failed to read mbox {}", doc.path);
+                return;
+            }
+        };
+        let name = format!("message {}", i + 1);
+        let message = match MessageParser::default().parse(entry.contents()) {
+            Some(m) => m,
+            None => {
+                log::error!("failed to parse {}!/{}", doc.path, name);
+                continue;
+            }
+        };
+        let member = Document {
+            path: format!("{}!/{}", doc.path, name),
+            name: doc.name.clone(),
+            data: entry.contents(),
+            format: Format::Email,
+            hint: None,
+            depth: doc.depth,
+            notes: doc.notes.clone(),
+        };
+        scan_message(&member, &message, "/", ctx);
+    }
+}
+
+/// Records the headers and bodies of a parsed message, then scans each attachment under
+/// `{message}{separator}attachment: {filename}`.
+fn scan_message(doc: &Document, message: &Message, separator: &str, ctx: &mut ScanContext) {
+    let mut segments = vec![Segment {
+        location: Some("headers".to_string()),
+        text: header_text(message),
+    }];
+
+    // Alternative html bodies are scanned too, as they don't always match the plain text.
+    let html_only = message
+        .html_body
+        .iter()
+        .filter(|id| !message.text_body.contains(id));
+    for id in message.text_body.iter().chain(html_only) {
+        if let Some(text) = message.parts.get(*id).and_then(|p| part_text(&p.body)) {
+            segments.push(Segment {
+                location: Some("body".to_string()),
+                text,
+            });
+        }
+    }
+    record_segments(doc, segments, ctx);
+
+    if message.attachment_count() == 0 || !container::can_open(doc, ctx) {
+        return;
+    }
+    for (i, part) in message.attachments().enumerate() {
+        let name = match part.attachment_name() {
+            Some(n) => n.to_string(),
+            None => format!("attachment {}", i + 1),
+        };
+        let path = format!("{}{}attachment: {}", doc.path, separator, name);
+        let hint = Format::from_name(&name, &ctx.settings.extensions);
+        let data = part.contents();
+        if data.len() as u64 > ctx.remaining_bytes {
+            log::warn!(
+                "stopped unpacking {}: container size limit of {} bytes reached",
+                doc.path,
+                ctx.settings.max_container_bytes
+            );
+            return;
+        }
+        ctx.remaining_bytes -= data.len() as u64;
+
+        match &part.body {
+            PartType::Message(nested) => {
+                let attachment = Document {
+                    path,
+                    name,
+                    data,
+                    format: Format::Email,
+                    hint: None,
+                    depth: doc.depth + 1,
+                    notes: Vec::new(),
+                };
+                scan_message(&attachment, nested, "/", ctx);
+            }
+            PartType::Text(_) | PartType::Html(_) if hint.is_none() => {
+                if let Some(text) = part_text(&part.body) {
+                    let attachment = Document {
+                        path,
+                        name,
+                        data,
+                        format: Format::Text,
+                        hint: None,
+                        depth: doc.depth + 1,
+                        notes: Vec::new(),
+                    };
+                    record_segments(&attachment, vec![whole_part(text)], ctx);
+                }
+            }
+            _ => {
+                match Document::detect(path.clone(), name, hint, data, doc.depth + 1, ctx.settings)
+                {
+                    Some(attachment) => scan_document(&attachment, ctx),
+                    None => {
+                        if ctx.settings.verbose {
+                            println!("Did not scan: {:#}", path)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Decodes a text or html body part to plain text.
+fn part_text(body: &PartType) -> Option<String> {
+    match body {
+        PartType::Text(text) => Some(text.to_string()),
+        PartType::Html(html) => Some(html_to_text(html)),
+        _ => None,
+    }
+}
+
+fn whole_part(text: String) -> Segment {
+    Segment {
+        location: None,
+        text,
+    }
+}
+
+/// Renders the decoded message headers one per line, e.g. `To: Jane Doe <jane@example.com>`.
+/// Encoded words are already decoded by the parser.
+fn header_text(message: &Message) -> String {
+    let mut text = String::new();
+    for header in message.headers() {
+        let value = match &header.value {
+            HeaderValue::Text(t) => t.to_string(),
+            HeaderValue::TextList(list) => list.join(", "),
+            HeaderValue::Address(address) => address
+                .iter()
+                .map(|addr| match (addr.name(), addr.address()) {
+                    (Some(name), Some(address)) => format!("{} <{}>", name, address),
+                    (name, address) => name.or(address).unwrap_or_default().to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => continue,
+        };
+        text.push_str(&format!("{}: {}\n", header.name.as_str(), value));
+    }
+    text
+}
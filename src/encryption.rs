@@ -7,11 +7,12 @@ use pbkdf2::{
 #[allow(unused, dead_code)]
 use std::error::Error;
 use std::io::ErrorKind;
-use std::iter::repeat;
 use std::{io, str};
 
-fn split_iv_data_mac(original: &str) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), Box<dyn Error>> {
-    let split: Vec<&str> = original.split('/').into_iter().collect();
+type IvDataMac = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+fn split_iv_data_mac(original: &str) -> Result<IvDataMac, Box<dyn Error>> {
+    let split: Vec<&str> = original.split('/').collect();
 
     if split.len() != 3 {
         return Err(Box::new(io::Error::from(ErrorKind::Other)));
@@ -40,13 +41,7 @@ fn split_iv_data_mac(original: &str) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), Box<
 
 fn get_valid_key(key: &str) -> Vec<u8> {
     let mut bytes = key.as_bytes().to_vec();
-    if bytes.len() < 16 {
-        for _ in 0..(16 - bytes.len()) {
-            bytes.push(0x00);
-        }
-    } else if bytes.len() > 16 {
-        bytes = bytes[0..16].to_vec();
-    }
+    bytes.resize(16, 0x00);
 
     bytes
 }
@@ -59,7 +54,7 @@ pub fn decrypt(iv_data_mac: &str, key: &str) -> Result<Vec<u8>, Box<dyn Error>>
 
     let mut decipher = AesGcm::new(key_size, &key, &iv, &[]);
 
-    let mut dst: Vec<u8> = repeat(0).take(data.len()).collect();
+    let mut dst: Vec<u8> = vec![0; data.len()];
     decipher.decrypt(&data, &mut dst, &mac);
 
     Ok(dst)
@@ -81,9 +76,9 @@ pub fn encrypt(data: &[u8], password: &str) -> String {
     let iv = get_iv(12);
     let mut cipher = AesGcm::new(key_size, &valid_key, &iv, &[]);
 
-    let mut encrypted: Vec<u8> = repeat(0).take(data.len()).collect();
+    let mut encrypted: Vec<u8> = vec![0; data.len()];
 
-    let mut mac: Vec<u8> = repeat(0).take(16).collect();
+    let mut mac: Vec<u8> = vec![0; 16];
 
     cipher.encrypt(data, &mut encrypted, &mut mac[..]);
 
@@ -106,7 +101,7 @@ pub fn hash_password(password: &str) -> Result<String, pbkdf2::password_hash::Er
 
 pub fn verify_password(
     password: &str,
-    password_hash: &String,
+    password_hash: &str,
 ) -> Result<bool, pbkdf2::password_hash::Error> {
     let parsed_hash = PasswordHash::new(password_hash)?;
     Ok(Pbkdf2
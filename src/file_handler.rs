@@ -1,259 +1,801 @@
-use msg_parser::Outlook;
-use regex::Regex;
-use std::fs;
-use std::io::BufReader;
-use std::io::Read;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
 use zip;
 
-use xml::reader::EventReader;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::container::PartLimits;
+use crate::patterns::Patterns;
+use crate::plugin::Plugin;
+use crate::{
+    container, delimited, email, legacy_office, markup, ole, ooxml, outlook, pdf, plugin, rtf,
+    sniff, spreadsheet, strings, structured, text_encoding, wordperfect,
+};
+
+/// Findings for a single document, or for one location inside it such as `Sheet1!B7`.
+/// Documents unpacked from a container carry a composite path such as
+/// `backup.zip!/hr/2024/payroll.xlsx`.
+#[derive(Debug)]
+pub struct FileFindings {
+    pub findings: Vec<Finding>,
+    /// Number of matches of each rule that matched, by index in the rule list.
+    pub match_counts: Vec<(usize, usize)>,
+    pub filename: String,
+    pub path: String,
+    pub location: Option<String>,
+    pub notes: Vec<String>,
+}
 
-pub fn scan_file(
-    path: &PathBuf,
-    patterns: &Vec<Regex>,
-    verbose: bool,
-) -> Option<(Vec<String>, String)> {
-    let ret = match path.extension() {
-        Some(ext) => match ext.to_str() {
-            Some("pdf") => {
-                if verbose {
-                    println!("Scanning: {:#}", path.display())
-                }
-                scan_pdf(&path, &patterns)
-            }
-            Some("xlsx") | Some("pptx") | Some("docx") => {
-                if verbose {
-                    println!("Scanning: {:#}", path.display())
-                }
-                scan_ooxml(&path, &patterns)
-            }
-            Some("txt") | Some("xml") | Some("html") | Some("htm") | Some("csv") => {
-                if verbose {
-                    println!("Scanning: {:#}", path.display())
-                }
-                scan_txt(&path, &patterns)
-            }
-            Some("rtf") | Some("wpd") => {
-                if verbose {
-                    println!("Scanning: {:#}", path.display())
-                }
-                scan_rtf(&path, &patterns)
-            }
-            Some("doc") | Some("ppt") | Some("xls") => {
-                if verbose {
-                    println!("Scanning: {:#}", path.display())
-                }
-                scan_legacy_office(&path, &patterns)
-            }
-            Some("msg") => {
-                if verbose {
-                    println!("Scanning: {:#}", path.display())
-                }
-                scan_msg(&path, &patterns)
-            }
-            _ => {
-                if verbose {
-                    println!("Did not scan: {:#}", path.display())
-                }
-                None
-            }
-        },
-        _ => None,
-    };
+/// A matched value and the index of the rule that found it, which is a key rule for values
+/// reported because of the key they are under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: usize,
+    pub value: String,
+}
 
-    ret
+/// A run of extracted text and where in the document it came from.
+#[derive(Debug)]
+pub struct Segment {
+    pub location: Option<String>,
+    pub text: String,
 }
 
-fn scan_msg(path: &PathBuf, patterns: &Vec<Regex>) -> Option<(Vec<String>, String)> {
-    let content = match Outlook::from_path(path) {
-        Ok(c) => c,
-        Err(e) => {
-            log::error!("failed to load msg file: {}", e);
-            return None;
-        }
-    };
-    let content = match content.to_json() {
-        Ok(c) => c,
-        Err(e) => {
-            log::error!("failed to load msg json: {}", e);
-            return None;
-        }
-    };
+/// Settings that control how documents are extracted.
+#[derive(Debug, Clone)]
+pub struct ExtractSettings {
+    pub verbose: bool,
+    /// Number of container levels that are opened, 0 disables container scanning. Single
+    /// compressed files such as `.log.gz` are decompressed one level further.
+    pub max_container_depth: usize,
+    /// Largest uncompressed to compressed size ratio accepted for a container member.
+    pub max_container_ratio: u64,
+    /// Total number of bytes that may be unpacked out of a single file on disk.
+    pub max_container_bytes: u64,
+    /// Largest size a gzip, bzip2, xz or zstd stream is decompressed to.
+    pub max_decompressed_bytes: u64,
+    /// Also search the attribute values of HTML and XML documents.
+    pub markup_attributes: bool,
+    /// Percentage of sampled cells in a CSV or TSV column that must match the same rule for
+    /// the whole column to be flagged, 0 disables column flagging.
+    pub column_threshold: u8,
+    /// Number of data rows sampled for column flagging.
+    pub column_sample_rows: usize,
+    /// Also search the key names of JSON, YAML and TOML documents.
+    pub structured_keys: bool,
+    /// Search the printable strings of files no extractor recognises.
+    pub strings_fallback: bool,
+    /// Shortest run of printable characters kept by the strings fallback.
+    pub strings_min_length: usize,
+    /// Number of distinct values kept for the report per file on disk.
+    pub max_distinct_values: usize,
+    /// Extractor picked for each lower-case file extension.
+    pub extensions: BTreeMap<String, Format>,
+    /// External commands that extract the file types they are registered for.
+    pub plugins: Vec<Plugin>,
+}
 
-    let findings = search_content(content, patterns);
+/// A document being scanned, either a file on disk or a member of a container.
+pub struct Document<'a> {
+    pub path: String,
+    pub name: String,
+    pub data: &'a [u8],
+    pub format: Format,
+    /// The format the name suggests, if any.
+    pub hint: Option<Format>,
+    pub depth: usize,
+    /// Remarks about the document itself that go in the report, such as a name and content
+    /// that disagree.
+    pub notes: Vec<String>,
+}
 
-    if findings.len() > 0 {
-        return Some((findings, path.to_str().unwrap().to_string()));
+impl<'a> Document<'a> {
+    /// Builds a document whose format is detected from its content, using the format its
+    /// name suggests as a hint. A plugin registered for the file takes precedence. Returns
+    /// `None` when nothing recognises the file, unless the strings fallback is enabled.
+    pub fn detect(
+        path: String,
+        name: String,
+        hint: Option<Format>,
+        data: &'a [u8],
+        depth: usize,
+        settings: &ExtractSettings,
+    ) -> Option<Self> {
+        if let Some(plugin) = plugin::find(&name, data, &settings.plugins) {
+            let notes = vec![format!("extracted by plugin {}", plugin.name)];
+            return Some(Document {
+                path,
+                name,
+                data,
+                format: Format::External,
+                hint,
+                depth,
+                notes,
+            });
+        }
+        let (format, notes) = match sniff::resolve(hint, sniff::sniff(data)) {
+            Some((format, mismatch)) => {
+                if let Some(note) = &mismatch {
+                    log::warn!("{}: {}", path, note);
+                }
+                (format, mismatch.into_iter().collect())
+            }
+            None if settings.strings_fallback && !data.is_empty() => {
+                (Format::Strings, vec![strings::LOW_CONFIDENCE.to_string()])
+            }
+            None => return None,
+        };
+        Some(Document {
+            path,
+            name,
+            data,
+            format,
+            hint,
+            depth,
+            notes,
+        })
     }
-
-    None
 }
 
-fn scan_pdf(path: &PathBuf, patterns: &Vec<Regex>) -> Option<(Vec<String>, String)> {
-    use lopdf::Document;
-
-    let content = match Document::load(path) {
-        Ok(doc) => {
-            let pages = doc.get_pages();
-            let mut texts = String::new();
+/// State shared by every document unpacked from the same file on disk.
+pub struct ScanContext<'a> {
+    pub patterns: &'a Patterns,
+    pub settings: &'a ExtractSettings,
+    pub remaining_bytes: u64,
+    /// Distinct values reported so far, capped at `max_distinct_values`.
+    pub distinct_values: HashSet<String>,
+    pub results: Vec<FileFindings>,
+}
 
-            for (i, _) in pages.iter().enumerate() {
-                let page_number = (i + 1) as u32;
-                let text = doc.extract_text(&[page_number]);
-                texts.push_str(&text.unwrap_or_default());
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    Pdf,
+    Ooxml,
+    Odf,
+    Text,
+    Delimited,
+    Markup,
+    Json,
+    JsonLines,
+    Yaml,
+    Toml,
+    Rtf,
+    WordPerfect,
+    LegacyOffice,
+    Spreadsheet,
+    Msg,
+    OleObject,
+    Email,
+    Mbox,
+    Zip,
+    Tar,
+    TarGz,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+    /// Data handed to an external extractor plugin.
+    External,
+    /// Unrecognised data, searched through its printable strings.
+    Strings,
+}
 
-            texts
-        }
-        Err(e) => {
-            log::error!("failed to load pdf: {}", e);
-            return None;
-        }
+/// Extensions mapped to an extractor when the config file doesn't say otherwise.
+const DEFAULT_EXTENSIONS: [(&str, Format); 81] = [
+    ("pdf", Format::Pdf),
+    ("docx", Format::Ooxml),
+    ("docm", Format::Ooxml),
+    ("dotx", Format::Ooxml),
+    ("dotm", Format::Ooxml),
+    ("pptx", Format::Ooxml),
+    ("pptm", Format::Ooxml),
+    ("potx", Format::Ooxml),
+    ("ppsx", Format::Ooxml),
+    ("xlsx", Format::Spreadsheet),
+    ("xlsm", Format::Spreadsheet),
+    ("xlsb", Format::Spreadsheet),
+    ("xls", Format::Spreadsheet),
+    ("ods", Format::Spreadsheet),
+    ("odt", Format::Odf),
+    ("odp", Format::Odf),
+    ("odg", Format::Odf),
+    ("txt", Format::Text),
+    ("log", Format::Text),
+    ("md", Format::Text),
+    ("rst", Format::Text),
+    ("sql", Format::Text),
+    ("ini", Format::Text),
+    ("cfg", Format::Text),
+    ("conf", Format::Text),
+    ("config", Format::Text),
+    ("properties", Format::Text),
+    ("env", Format::Text),
+    ("npmrc", Format::Text),
+    ("pgpass", Format::Text),
+    ("netrc", Format::Text),
+    ("git-credentials", Format::Text),
+    ("ps1", Format::Text),
+    ("psm1", Format::Text),
+    ("bat", Format::Text),
+    ("cmd", Format::Text),
+    ("sh", Format::Text),
+    ("bash", Format::Text),
+    ("zsh", Format::Text),
+    ("py", Format::Text),
+    ("rb", Format::Text),
+    ("pl", Format::Text),
+    ("php", Format::Text),
+    ("js", Format::Text),
+    ("ts", Format::Text),
+    ("java", Format::Text),
+    ("cs", Format::Text),
+    ("go", Format::Text),
+    ("rs", Format::Text),
+    ("c", Format::Text),
+    ("h", Format::Text),
+    ("cpp", Format::Text),
+    ("tf", Format::Text),
+    ("tfvars", Format::Text),
+    ("csv", Format::Delimited),
+    ("tsv", Format::Delimited),
+    ("tab", Format::Delimited),
+    ("psv", Format::Delimited),
+    ("html", Format::Markup),
+    ("htm", Format::Markup),
+    ("xhtml", Format::Markup),
+    ("xml", Format::Markup),
+    ("json", Format::Json),
+    ("ndjson", Format::JsonLines),
+    ("jsonl", Format::JsonLines),
+    ("yaml", Format::Yaml),
+    ("yml", Format::Yaml),
+    ("toml", Format::Toml),
+    ("rtf", Format::Rtf),
+    ("wpd", Format::WordPerfect),
+    ("wp", Format::WordPerfect),
+    ("wp5", Format::WordPerfect),
+    ("wp6", Format::WordPerfect),
+    ("doc", Format::LegacyOffice),
+    ("ppt", Format::LegacyOffice),
+    ("msg", Format::Msg),
+    ("eml", Format::Email),
+    ("mbox", Format::Mbox),
+    ("mbx", Format::Mbox),
+    ("zip", Format::Zip),
+    ("tar", Format::Tar),
+];
+
+/// Compression and archive suffixes, which aren't configurable.
+const ARCHIVE_EXTENSIONS: [(&str, Format); 5] = [
+    ("tgz", Format::TarGz),
+    ("gz", Format::Gzip),
+    ("bz2", Format::Bzip2),
+    ("xz", Format::Xz),
+    ("zst", Format::Zstd),
+];
+
+/// Returns the lower-case extension of a file name. Dotfiles such as `.env` count as their
+/// whole name.
+pub fn extension(name: &str) -> Option<String> {
+    let path = Path::new(name);
+    let extension = match path.extension() {
+        Some(e) => e.to_str()?,
+        None => path.file_name()?.to_str()?.strip_prefix('.')?,
     };
+    Some(extension.to_lowercase())
+}
 
-    let findings = search_content(content, patterns);
-
-    if findings.len() > 0 {
-        return Some((findings, path.to_str().unwrap().to_string()));
+impl Format {
+    /// The default extension to extractor mapping stored in a new config file.
+    pub fn default_extensions() -> BTreeMap<String, Format> {
+        DEFAULT_EXTENSIONS
+            .iter()
+            .map(|(extension, format)| (extension.to_string(), *format))
+            .collect()
     }
 
-    None
-}
+    /// Picks the extractor a file name suggests through the configured `extensions`.
+    /// Content sniffing has the final say, see [`Document::detect`].
+    pub fn from_name(name: &str, extensions: &BTreeMap<String, Format>) -> Option<Format> {
+        if name.to_lowercase().ends_with(".tar.gz") {
+            return Some(Format::TarGz);
+        }
+        let extension = extension(name)?;
+        ARCHIVE_EXTENSIONS
+            .iter()
+            .find(|(e, _)| *e == extension)
+            .map(|(_, format)| *format)
+            .or_else(|| extensions.get(&extension).copied())
+    }
 
-fn scan_ooxml(path: &PathBuf, patterns: &Vec<Regex>) -> Option<(Vec<String>, String)> {
-    let file = fs::File::open(path).unwrap();
-    let mut archive = match zip::ZipArchive::new(file) {
-        Ok(z) => z,
-        Err(_) => return None,
-    };
-    let mut items = vec![];
-    let mut content = String::new();
-    for item in archive.file_names() {
-        items.push(item.to_owned());
+    /// Parses the name an extractor goes by in the config file, such as `text`.
+    pub fn from_key(key: &str) -> Option<Format> {
+        let key = serde::de::value::StrDeserializer::<serde::de::value::Error>::new(key);
+        Format::deserialize(key).ok()
     }
-    for item in items {
-        let reader = BufReader::new(match archive.by_name(&item) {
-            Ok(i) => i,
-            Err(_) => continue,
-        });
-        let parser = EventReader::new(reader);
-        for event in parser {
-            match event {
-                Ok(xml::reader::XmlEvent::Characters(c)) => {
-                    content.push_str(&c);
-                }
-                _ => continue,
-            }
+
+    /// The name an extractor goes by in the config file.
+    pub fn key(&self) -> String {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(key)) => key,
+            _ => format!("{:?}", self),
         }
     }
 
-    let findings = search_content(content, patterns);
+    /// Picks the extractor for a file on disk. Maildir messages have no extension of their
+    /// own and are recognised by the `cur` or `new` folder they are delivered to.
+    pub fn from_path(path: &Path, extensions: &BTreeMap<String, Format>) -> Option<Format> {
+        let name = path.file_name()?.to_str()?;
+        Format::from_name(name, extensions).or_else(|| {
+            match path.parent()?.file_name()?.to_str()? {
+                "cur" | "new" => Some(Format::Email),
+                _ => None,
+            }
+        })
+    }
 
-    if findings.len() > 0 {
-        return Some((findings, path.to_str().unwrap().to_string()));
+    /// Formats that are plain text and can only be told apart by name.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Format::Text
+                | Format::Delimited
+                | Format::Markup
+                | Format::Json
+                | Format::JsonLines
+                | Format::Yaml
+                | Format::Toml
+                | Format::Email
+                | Format::Mbox
+        )
     }
 
-    None
+    /// A short description used in reports.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Format::Pdf => "PDF",
+            Format::Ooxml => "Word/PowerPoint (OOXML)",
+            Format::Odf => "OpenDocument",
+            Format::Text => "text",
+            Format::Delimited => "delimited text",
+            Format::Markup => "HTML/XML",
+            Format::Json => "JSON",
+            Format::JsonLines => "JSON Lines",
+            Format::Yaml => "YAML",
+            Format::Toml => "TOML",
+            Format::Rtf => "RTF",
+            Format::WordPerfect => "WordPerfect",
+            Format::LegacyOffice => "legacy Office",
+            Format::Spreadsheet => "spreadsheet",
+            Format::Msg => "Outlook message",
+            Format::OleObject => "OLE object",
+            Format::Email => "email",
+            Format::Mbox => "mbox",
+            Format::Zip => "zip",
+            Format::Tar => "tar",
+            Format::TarGz => "tar.gz",
+            Format::Gzip => "gzip",
+            Format::Bzip2 => "bzip2",
+            Format::Xz => "xz",
+            Format::Zstd => "zstd",
+            Format::External => "external extractor",
+            Format::Strings => "printable strings",
+        }
+    }
 }
 
-fn scan_legacy_office(path: &PathBuf, patterns: &Vec<Regex>) -> Option<(Vec<String>, String)> {
-    let mut file = match fs::File::open(path) {
-        Ok(f) => f,
-        Err(e) => {
-            log::error!("failed to open legacy office file: {}", e);
-            return None;
-        }
+pub fn scan_file(
+    path: &Path,
+    patterns: &Patterns,
+    settings: &ExtractSettings,
+) -> Vec<FileFindings> {
+    let name = match path.file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => return Vec::new(),
     };
-    let mut buffer = vec![];
-    match file.read_to_end(&mut buffer) {
-        Ok(_) => (),
+    if !path.is_file() {
+        return Vec::new();
+    }
+    let hint = Format::from_path(path, &settings.extensions);
+
+    let mut file = match File::open(path) {
+        Ok(f) => f,
         Err(e) => {
-            log::error!("legacy office file failed to read: {}", e);
-            return None;
+            log::error!("failed to read file: {}", e);
+            return Vec::new();
         }
     };
-    let mut content = String::new();
-    for c in &buffer {
-        if c.is_ascii() {
-            let ch = *c as char;
-            if ch.is_ascii_alphanumeric() {
-                content.push(ch);
-            }
-            if ch.is_ascii_whitespace() {
-                content.push(' ');
+    // Only the head is read at first so unrecognised files aren't loaded in full.
+    let mut data = Vec::new();
+    let read = (&mut file)
+        .take(sniff::HEAD_LEN as u64)
+        .read_to_end(&mut data)
+        .and_then(|_| match is_scannable(&name, hint, &data, settings) {
+            true => file.read_to_end(&mut data),
+            false => Ok(0),
+        });
+    if let Err(e) = read {
+        log::error!("failed to read file: {}", e);
+        return Vec::new();
+    }
+
+    let doc = match Document::detect(
+        path.to_string_lossy().to_string(),
+        name,
+        hint,
+        &data,
+        0,
+        settings,
+    ) {
+        Some(d) => d,
+        None => {
+            if settings.verbose {
+                println!("Did not scan: {:#}", path.display())
             }
+            return Vec::new();
         }
-    }
+    };
+    let mut ctx = ScanContext {
+        patterns,
+        settings,
+        remaining_bytes: settings.max_container_bytes,
+        distinct_values: HashSet::new(),
+        results: Vec::new(),
+    };
+    scan_document(&doc, &mut ctx);
 
-    let findings = search_content(content, patterns);
+    ctx.results
+}
+
+/// Tells from a file's name and the first bytes of its content whether anything can
+/// extract it, so unrecognised files don't have to be read in full.
+pub fn is_scannable(
+    name: &str,
+    hint: Option<Format>,
+    head: &[u8],
+    settings: &ExtractSettings,
+) -> bool {
+    hint.is_some()
+        || settings.strings_fallback
+        || sniff::sniff(head).is_some()
+        || plugin::find(name, head, &settings.plugins).is_some()
+}
 
-    if findings.len() > 0 {
-        return Some((findings, path.to_str().unwrap().to_string()));
+/// Runs a document through the extractor for its format and records any findings.
+/// Containers are unpacked and each member is scanned in turn.
+pub fn scan_document(doc: &Document, ctx: &mut ScanContext) {
+    if ctx.settings.verbose {
+        println!("Scanning: {:#}", doc.path)
     }
 
-    None
+    let segments = match doc.format {
+        Format::Msg => return outlook::scan_msg(doc, ctx),
+        Format::Email => return email::scan_email(doc, ctx),
+        Format::Mbox => return email::scan_mbox(doc, ctx),
+        Format::Zip => return container::scan_zip(doc, ctx),
+        Format::Tar => return container::scan_tar(doc, ctx),
+        Format::TarGz => return container::scan_tar_gz(doc, ctx),
+        Format::Gzip | Format::Bzip2 | Format::Xz | Format::Zstd => {
+            return container::scan_compressed(doc, ctx)
+        }
+        Format::External => return plugin::scan_external(doc, ctx),
+        Format::OleObject => return ole::scan_ole_object(doc, ctx),
+        Format::Pdf => {
+            if !pdf::scan_pdf(doc, ctx) {
+                scan_as_named(doc, ctx);
+            }
+            return;
+        }
+        Format::Spreadsheet => match container::check_parts(doc.data, PartLimits::new(ctx)) {
+            true => spreadsheet::extract(doc.data),
+            false => return,
+        },
+        Format::Ooxml => ooxml::extract(doc.data, PartLimits::new(ctx)),
+        Format::Odf => extract_odf(doc.data, PartLimits::new(ctx)).map(whole_document),
+        Format::Text
+        | Format::Delimited
+        | Format::Markup
+        | Format::Json
+        | Format::JsonLines
+        | Format::Yaml
+        | Format::Toml => return scan_text(doc, ctx),
+        Format::Rtf => rtf::extract(doc.data).map(whole_document),
+        Format::WordPerfect => wordperfect::extract(doc.data).map(whole_document),
+        Format::LegacyOffice => legacy_office::extract(doc.data, ctx.settings.strings_min_length),
+        Format::Strings => Some(strings::extract(doc.data, ctx.settings.strings_min_length)),
+    };
+    match segments {
+        Some(segments) => record_segments(doc, segments, ctx),
+        None => scan_as_named(doc, ctx),
+    }
+    // Excel 97 workbooks keep their embedded objects in storages that aren't unpacked.
+    if matches!(doc.format, Format::Ooxml | Format::Spreadsheet) && doc.data.starts_with(b"PK") {
+        container::scan_embeddings(doc, ctx);
+    }
 }
 
-fn scan_txt(path: &PathBuf, patterns: &Vec<Regex>) -> Option<(Vec<String>, String)> {
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(e) => {
-            log::error!("ERROR reading txt file: {}", e);
-            return None;
-        }
+/// Scans a document whose content failed to extract as the text its name says it is, such
+/// as a script that mentions a PDF header near its top. Other documents are left alone.
+fn scan_as_named(doc: &Document, ctx: &mut ScanContext) {
+    let hint = match doc.hint {
+        Some(hint) if hint.is_text() => hint,
+        _ => return,
+    };
+    log::warn!(
+        "{}: could not be read as {}, scanning it as {}",
+        doc.path,
+        doc.format.label(),
+        hint.label()
+    );
+    let mut notes = doc.notes.clone();
+    notes.push(format!(
+        "read as {} after {} extraction failed",
+        hint.label(),
+        doc.format.label()
+    ));
+    let doc = Document {
+        path: doc.path.clone(),
+        name: doc.name.clone(),
+        format: hint,
+        notes,
+        ..*doc
     };
+    scan_text(&doc, ctx);
+}
+
+/// Searches each segment of a document and records the ones with findings.
+/// Once a file has `max_distinct_values` distinct values, new ones are only counted.
+pub fn record_segments(doc: &Document, segments: Vec<Segment>, ctx: &mut ScanContext) {
+    for segment in segments {
+        let matches = search_content(&segment.text, ctx.patterns);
+        record_matches(doc, segment.location, matches, ctx);
+    }
+}
 
-    let findings = search_content(content, patterns);
+/// Records what was found in one segment, for callers that searched it themselves. See
+/// [`record_segments`].
+pub fn record_matches(
+    doc: &Document,
+    location: Option<String>,
+    matches: Matches,
+    ctx: &mut ScanContext,
+) {
+    for (index, count) in &matches.rejected {
+        ctx.patterns.add_rejected(*index, *count);
+    }
+    if matches.counts.is_empty() {
+        return;
+    }
 
-    if findings.len() > 0 {
-        return Some((findings, path.to_str().unwrap().to_string()));
+    let mut capped = false;
+    let mut findings = Vec::with_capacity(matches.values.len());
+    for finding in matches.values {
+        if ctx.distinct_values.contains(&finding.value) {
+            findings.push(finding);
+        } else if ctx.distinct_values.len() < ctx.settings.max_distinct_values {
+            ctx.distinct_values.insert(finding.value.clone());
+            findings.push(finding);
+        } else {
+            capped = true;
+        }
+    }
+    let mut notes = doc.notes.clone();
+    if capped {
+        notes.push(format!(
+            "more than {} distinct values, the rest are only counted",
+            ctx.settings.max_distinct_values
+        ));
     }
+    ctx.results.push(FileFindings {
+        findings,
+        match_counts: matches.counts,
+        filename: doc.name.clone(),
+        path: doc.path.clone(),
+        location,
+        notes,
+    });
+}
 
-    None
+/// Wraps text from an extractor that has no finer location than the document itself.
+pub fn whole_document(text: String) -> Vec<Segment> {
+    vec![Segment {
+        location: None,
+        text,
+    }]
 }
 
-fn scan_rtf(path: &PathBuf, patterns: &Vec<Regex>) -> Option<(Vec<String>, String)> {
-    let file = match fs::File::open(path) {
-        Ok(f) => f,
+/// Parts of an OpenDocument package that carry user visible text.
+const ODF_PARTS: [&str; 3] = ["content.xml", "styles.xml", "meta.xml"];
+
+fn extract_odf(data: &[u8], limits: PartLimits) -> Option<String> {
+    let mut archive = match zip::ZipArchive::new(Cursor::new(data)) {
+        Ok(z) => z,
         Err(e) => {
-            log::error!("ERROR rtf failed to load: {}", e);
+            log::error!("failed to open OpenDocument package: {}", e);
             return None;
         }
     };
-    let mut reader = BufReader::new(file);
     let mut content = String::new();
-    match reader.read_to_string(&mut content) {
-        Ok(_) => (),
-        Err(e) => {
-            log::error!("ERROR processing rtf: {}", e);
+    for part in ODF_PARTS {
+        if let Some(xml) = container::read_part(&mut archive, part, limits) {
+            content.push_str(&odf_text(xml.as_slice()));
         }
     }
 
-    let findings = search_content(content, patterns);
+    Some(content)
+}
 
-    if findings.len() > 0 {
-        return Some((findings, path.to_str().unwrap().to_string()));
+/// Collects the text of an OpenDocument xml part.
+///
+/// Only character data inside paragraphs and headings (which also covers list items,
+/// notes and frame text boxes), drawing titles/descriptions and document metadata is kept,
+/// so style names and other markup never reach the haystack. Each block ends with a newline.
+fn odf_text<R: Read>(reader: R) -> String {
+    let mut text = String::new();
+    let mut block_depth = 0;
+    let mut skip_depth = 0;
+    let mut in_meta = false;
+
+    for event in EventReader::new(reader) {
+        match event {
+            Ok(XmlEvent::StartElement { name, .. }) => {
+                match (name.prefix.as_deref(), name.local_name.as_str()) {
+                    (Some("text"), "p") | (Some("text"), "h") => block_depth += 1,
+                    (Some("svg"), "title") | (Some("svg"), "desc") => block_depth += 1,
+                    (Some("text"), "note-citation") => skip_depth += 1,
+                    // A run of spaces never changes a match, so `text:c` is not expanded.
+                    (Some("text"), "s") => text.push(' '),
+                    (Some("text"), "tab") => text.push('\t'),
+                    (Some("text"), "line-break") => text.push('\n'),
+                    (Some("office"), "meta") => in_meta = true,
+                    _ => (),
+                }
+            }
+            Ok(XmlEvent::EndElement { name }) => {
+                match (name.prefix.as_deref(), name.local_name.as_str()) {
+                    (Some("text"), "p")
+                    | (Some("text"), "h")
+                    | (Some("svg"), "title")
+                    | (Some("svg"), "desc") => {
+                        block_depth -= 1;
+                        text.push('\n');
+                    }
+                    (Some("text"), "note-citation") => skip_depth -= 1,
+                    (Some("office"), "meta") => in_meta = false,
+                    _ if in_meta => text.push('\n'),
+                    _ => (),
+                }
+            }
+            Ok(XmlEvent::Characters(c)) | Ok(XmlEvent::Whitespace(c)) | Ok(XmlEvent::CData(c)) => {
+                if (block_depth > 0 || in_meta) && skip_depth == 0 {
+                    text.push_str(&c);
+                }
+            }
+            Ok(_) => (),
+            Err(e) => {
+                log::error!("failed to parse OpenDocument xml: {}", e);
+                break;
+            }
+        }
     }
 
-    None
+    text
 }
 
-fn search_content(content: String, patterns: &Vec<Regex>) -> Vec<String> {
-    let mut findings = Vec::new();
+/// Decodes a plain text or markup document and notes the encoding it was read as in the
+/// report.
+fn scan_text(doc: &Document, ctx: &mut ScanContext) {
+    let (text, encoding) = text_encoding::decode(doc.data);
+    let mut notes = doc.notes.clone();
+    notes.push(format!("encoding: {}", encoding.name()));
+    let doc = Document {
+        path: doc.path.clone(),
+        name: doc.name.clone(),
+        notes,
+        ..*doc
+    };
 
-    for pattern in patterns.iter() {
-        match pattern.captures(&content) {
-            Some(cap) => {
-                for finding in cap.iter() {
-                    let finding = finding.unwrap().as_str().to_string();
-                    if !findings.contains(&finding) {
-                        findings.push(finding);
-                    }
+    let mut keyed_values = Vec::new();
+    let segments = match doc.format {
+        Format::Delimited => return delimited::scan(&doc, &text, ctx),
+        Format::Markup => markup::extract(&text, ctx.settings.markup_attributes),
+        Format::Json | Format::JsonLines | Format::Yaml | Format::Toml => {
+            match structured::extract(&text, doc.format, ctx.settings, ctx.patterns) {
+                Some(extracted) => {
+                    keyed_values = extracted.keyed_values;
+                    extracted.segments
                 }
+                None => whole_document(text),
             }
-            None => (),
+        }
+        _ => whole_document(text),
+    };
+    record_segments(&doc, segments, ctx);
+
+    // Values under a sensitive key are findings in themselves.
+    for (rule, value) in keyed_values {
+        ctx.results.push(FileFindings {
+            findings: vec![Finding {
+                rule,
+                value: value.text,
+            }],
+            match_counts: Vec::new(),
+            filename: doc.name.clone(),
+            path: doc.path.clone(),
+            location: value.location,
+            notes: doc.notes.clone(),
+        });
+    }
+}
+
+/// What the patterns found in a piece of text.
+#[derive(Debug, Default)]
+pub struct Matches {
+    /// Distinct matched values, each capture group included, in order of appearance.
+    pub values: Vec<Finding>,
+    /// Number of matches of each rule that matched, by index in the rule list.
+    pub counts: Vec<(usize, usize)>,
+    /// Number of matches each rule's validator rejected, by index in the rule list.
+    pub rejected: Vec<(usize, usize)>,
+}
+
+/// Finds every match of the patterns that can match, see [`Patterns::matching`]. Patterns
+/// with capture groups also report what each group captured, skipping optional groups
+/// that didn't take part in a match. Empty matches are ignored, and so are matches that
+/// fail the rule's validator, which are only counted.
+pub fn search_content(content: &str, patterns: &Patterns) -> Matches {
+    let mut matches = Matches::default();
+    let mut seen = HashSet::new();
+    let mut push = |rule: usize, value: &str, values: &mut Vec<Finding>| {
+        if !value.is_empty() && seen.insert(value.to_string()) {
+            values.push(Finding {
+                rule,
+                value: value.to_string(),
+            });
+        }
+    };
+
+    for (index, pattern) in patterns.matching(content) {
+        let validator = patterns.rule(index).validator;
+        let valid = |found: &str| validator.is_none_or(|v| v.validate(found));
+        let mut count = 0;
+        let mut rejected = 0;
+        if pattern.captures_len() == 1 {
+            for found in pattern.find_iter(content).filter(|m| !m.is_empty()) {
+                if !valid(found.as_str()) {
+                    rejected += 1;
+                    continue;
+                }
+                count += 1;
+                push(index, found.as_str(), &mut matches.values);
+            }
+        } else {
+            for captures in pattern.captures_iter(content) {
+                let found = match captures.get(0) {
+                    Some(m) if !m.is_empty() => m,
+                    _ => continue,
+                };
+                if !valid(found.as_str()) {
+                    rejected += 1;
+                    continue;
+                }
+                count += 1;
+                for group in captures.iter().flatten() {
+                    push(index, group.as_str(), &mut matches.values);
+                }
+            }
+        }
+        if count > 0 {
+            matches.counts.push((index, count));
+        }
+        if rejected > 0 {
+            matches.rejected.push((index, rejected));
         }
     }
 
-    findings
+    matches
 }
@@ -0,0 +1,236 @@
+use cfb::CompoundFile;
+use encoding_rs::WINDOWS_1252;
+use std::io::{self, Cursor, Read, Seek};
+
+use crate::file_handler::{whole_document, Segment};
+use crate::strings;
+
+/// Word 97 and later store the document text behind a piece table.
+const MIN_PIECE_TABLE_NFIB: u16 = 0x0065;
+const WORD_IDENT: u16 = 0xA5EC;
+
+// PowerPoint record types that carry text.
+const PPT_TEXT_CHARS_ATOM: u16 = 0x0FA0;
+const PPT_TEXT_BYTES_ATOM: u16 = 0x0FA8;
+const PPT_CSTRING: u16 = 0x0FBA;
+
+/// Extracts the text of a legacy (97-2003) Office document from its Compound File
+/// Binary container. Compound files with no known text stream are read for their printable
+/// strings of at least `strings_min_length` characters.
+pub fn extract(data: &[u8], strings_min_length: usize) -> Option<Vec<Segment>> {
+    let mut comp = match CompoundFile::open(Cursor::new(data)) {
+        Ok(c) => c,
+        Err(e) => {
+            log::error!("failed to open compound file: {}", e);
+            return None;
+        }
+    };
+
+    let result = if comp.is_stream("/WordDocument") {
+        extract_word(&mut comp)
+    } else if comp.is_stream("/PowerPoint Document") {
+        read_stream(&mut comp, "/PowerPoint Document").map(|s| extract_ppt(&s))
+    } else {
+        return Some(strings::extract(data, strings_min_length));
+    };
+
+    match result {
+        Ok(text) => Some(whole_document(text)),
+        Err(e) => {
+            log::error!("failed to read legacy office document: {}", e);
+            None
+        }
+    }
+}
+
+fn read_stream<F: Read + Seek>(comp: &mut CompoundFile<F>, name: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    comp.open_stream(name)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Reads the main document, footnotes, headers/footers, comments, endnotes and text boxes of
+/// a Word document. All of these live in the same character stream, addressed through the
+/// piece table stored in the table stream.
+fn extract_word<F: Read + Seek>(comp: &mut CompoundFile<F>) -> io::Result<String> {
+    let word = read_stream(comp, "/WordDocument")?;
+    if word.len() < 0x01AA || read_u16(&word, 0) != Some(WORD_IDENT) {
+        return Err(invalid("WordDocument stream has no valid FIB"));
+    }
+
+    let n_fib = read_u16(&word, 0x02).unwrap_or_default();
+    let flags = read_u16(&word, 0x0A).unwrap_or_default();
+    if flags & 0x0100 != 0 {
+        return Err(invalid("document is encrypted"));
+    }
+
+    if n_fib < MIN_PIECE_TABLE_NFIB {
+        // Word 6/95 keeps the text as one contiguous 8-bit run.
+        let fc_min = read_u32(&word, 0x18).unwrap_or_default() as usize;
+        let fc_mac = read_u32(&word, 0x1C).unwrap_or_default() as usize;
+        let text = word.get(fc_min..fc_mac.min(word.len())).unwrap_or_default();
+        let (decoded, _) = WINDOWS_1252.decode_without_bom_handling(text);
+        return Ok(word_text(decoded.chars()));
+    }
+
+    let table_name = match flags & 0x0200 {
+        0 => "/0Table",
+        _ => "/1Table",
+    };
+    let table = read_stream(comp, table_name)?;
+
+    // Character counts of every sub-document, stored in FibRgLw97.
+    let ccp_total = (0..8)
+        .filter(|i| *i != 3)
+        .map(|i| read_u32(&word, 0x4C + i * 4).unwrap_or_default())
+        .fold(0u32, u32::saturating_add);
+    let fc_clx = read_u32(&word, 0x01A2).unwrap_or_default() as usize;
+    let lcb_clx = read_u32(&word, 0x01A6).unwrap_or_default() as usize;
+    let clx = table
+        .get(fc_clx..fc_clx + lcb_clx)
+        .ok_or_else(|| invalid("piece table out of range"))?;
+    let pieces = piece_table(clx).ok_or_else(|| invalid("malformed piece table"))?;
+
+    let mut text = String::new();
+    for (cp_start, cp_end, fc) in pieces {
+        if cp_start >= ccp_total {
+            break;
+        }
+        let chars = (cp_end.min(ccp_total) - cp_start) as usize;
+        let compressed = fc & 0x4000_0000 != 0;
+        if compressed {
+            let offset = ((fc & 0x3FFF_FFFF) / 2) as usize;
+            if let Some(bytes) = word.get(offset..offset + chars) {
+                let (decoded, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+                text.push_str(&decoded);
+            }
+        } else {
+            let offset = fc as usize;
+            if let Some(bytes) = word.get(offset..offset + chars * 2) {
+                text.extend(utf16le(bytes));
+            }
+        }
+    }
+
+    Ok(word_text(text.chars()))
+}
+
+/// Walks the Clx structure and returns `(cp_start, cp_end, fc)` for each piece.
+fn piece_table(clx: &[u8]) -> Option<Vec<(u32, u32, u32)>> {
+    let mut pos = 0;
+    // Skip any Prc (property modifier) entries ahead of the Pcdt.
+    while *clx.get(pos)? == 0x01 {
+        let cb = read_u16(clx, pos + 1)? as usize;
+        pos += 3 + cb;
+    }
+    if *clx.get(pos)? != 0x02 {
+        return None;
+    }
+    let lcb = read_u32(clx, pos + 1)? as usize;
+    let plc = clx.get(pos + 5..pos + 5 + lcb)?;
+    if lcb < 4 {
+        return None;
+    }
+    let count = (lcb - 4) / 12;
+    let pcd_start = (count + 1) * 4;
+
+    let mut pieces = Vec::with_capacity(count);
+    for i in 0..count {
+        let cp_start = read_u32(plc, i * 4)?;
+        let cp_end = read_u32(plc, (i + 1) * 4)?;
+        let fc = read_u32(plc, pcd_start + i * 8 + 2)?;
+        if cp_end > cp_start {
+            pieces.push((cp_start, cp_end, fc));
+        }
+    }
+    Some(pieces)
+}
+
+/// Converts Word's special characters to plain text. Field instructions (between the field
+/// begin and separator marks) are dropped while the field result is kept.
+fn word_text<I: Iterator<Item = char>>(chars: I) -> String {
+    let mut text = String::new();
+    let mut field_codes = Vec::new();
+    for c in chars {
+        match c {
+            '\u{13}' => field_codes.push(true),
+            '\u{14}' => {
+                if let Some(code) = field_codes.last_mut() {
+                    *code = false;
+                }
+            }
+            '\u{15}' => {
+                field_codes.pop();
+            }
+            _ if field_codes.last() == Some(&true) => (),
+            '\r' | '\u{0B}' | '\u{0C}' | '\u{0E}' => text.push('\n'),
+            '\u{07}' | '\t' => text.push('\t'),
+            c if c.is_control() => (),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// Collects every text atom in the PowerPoint Document stream. Container records are
+/// descended into, so slide text, notes, comments and header/footer strings are all found.
+fn extract_ppt(stream: &[u8]) -> String {
+    let mut text = String::new();
+    let mut pos = 0;
+    while pos + 8 <= stream.len() {
+        let ver_instance = read_u16(stream, pos).unwrap_or_default();
+        let rec_type = read_u16(stream, pos + 2).unwrap_or_default();
+        let rec_len = read_u32(stream, pos + 4).unwrap_or_default() as usize;
+        pos += 8;
+
+        if ver_instance & 0x000F == 0x000F {
+            continue;
+        }
+        let body = match stream.get(pos..pos + rec_len) {
+            Some(b) => b,
+            None => break,
+        };
+        match rec_type {
+            PPT_TEXT_CHARS_ATOM | PPT_CSTRING => {
+                text.extend(utf16le(body));
+                text.push('\n');
+            }
+            PPT_TEXT_BYTES_ATOM => {
+                text.extend(body.iter().map(|b| *b as char));
+                text.push('\n');
+            }
+            _ => (),
+        }
+        pos += rec_len;
+    }
+
+    text.chars()
+        .map(|c| match c {
+            '\r' | '\u{0B}' => '\n',
+            c => c,
+        })
+        .collect()
+}
+
+fn utf16le(bytes: &[u8]) -> impl Iterator<Item = char> + '_ {
+    char::decode_utf16(
+        bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]])),
+    )
+    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
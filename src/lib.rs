@@ -1,12 +1,33 @@
 pub mod args;
+pub mod container;
 pub mod csv_writer;
+pub mod delimited;
+pub mod email;
 pub mod encryption;
 pub mod file_handler;
+pub mod legacy_office;
+pub mod markup;
+pub mod ole;
+pub mod ooxml;
+pub mod outlook;
+pub mod packs;
+pub mod patterns;
+pub mod pdf;
+pub mod plugin;
+pub mod rtf;
+pub mod rules;
 pub mod run;
 pub mod scan_manager;
 pub mod scan_settings;
 pub mod scanner;
 pub mod settings;
+pub mod sniff;
+pub mod spreadsheet;
+pub mod strings;
+pub mod structured;
+pub mod text_encoding;
+pub mod validators;
+pub mod wordperfect;
 
 pub mod sift {
     use serde::Serialize;
@@ -19,7 +40,11 @@ pub mod sift {
     #[derive(Serialize)]
     pub struct Row {
         pub findings: String,
+        pub matches: String,
+        pub severity: String,
         pub filename: String,
         pub path: String,
+        pub location: String,
+        pub notes: String,
     }
 }
@@ -0,0 +1,272 @@
+use html_escape::decode_html_entities;
+
+use crate::file_handler::Segment;
+
+/// Elements whose content is code or styling rather than text.
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// HTML elements that format text within a line. They don't separate the text on either
+/// side, so a value split by a tag such as `123-<b>45</b>-6789` still reads as one word.
+const INLINE_ELEMENTS: [&str; 18] = [
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "em", "font", "i", "mark", "q", "s", "small",
+    "span", "strong", "sub", "sup",
+];
+
+/// Extracts the text of an HTML or XML document.
+///
+/// Tags, comments, processing instructions and the content of scripts and styles are
+/// dropped, entities are decoded, and text nodes are separated by whitespace. Attribute
+/// values are returned as a separate `attribute values` segment when `attributes` is set.
+pub fn extract(source: &str, attributes: bool) -> Vec<Segment> {
+    let (text, values) = parse(source, attributes);
+    let mut segments = vec![Segment {
+        location: None,
+        text,
+    }];
+    if !values.is_empty() {
+        segments.push(Segment {
+            location: Some("attribute values".to_string()),
+            text: values,
+        });
+    }
+    segments
+}
+
+/// Converts an HTML body, such as an email's, to plain text.
+pub fn html_to_text(source: &str) -> String {
+    parse(source, false).0
+}
+
+/// Returns the text and, when `attributes` is set, the attribute values of a document.
+fn parse(source: &str, attributes: bool) -> (String, String) {
+    let mut text = String::new();
+    let mut values = String::new();
+    let mut rest = source;
+
+    while let Some(lt) = rest.find('<') {
+        push_text(&mut text, &rest[..lt]);
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = after(comment, "-->");
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            text.push_str(&cdata[..end]);
+            rest = after(cdata, "]]>");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = after(rest, ">");
+        } else if let Some(tag) = Tag::parse(rest) {
+            if !INLINE_ELEMENTS.contains(&tag.name.as_str()) {
+                text.push('\n');
+            }
+            if attributes {
+                for value in &tag.values {
+                    values.push_str(&decode_html_entities(value));
+                    values.push('\n');
+                }
+            }
+            rest = &rest[tag.len..];
+            if !tag.closing && !tag.self_closing && RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) {
+                rest = skip_raw_text(rest, &tag.name);
+            }
+        } else {
+            // A bare `<` that doesn't open a tag is text.
+            text.push('<');
+            rest = &rest[1..];
+        }
+    }
+    push_text(&mut text, rest);
+
+    (text, values)
+}
+
+/// Decodes the entities of a text node. Non-breaking spaces become plain spaces so they
+/// match `\s` and ` ` in patterns.
+fn push_text(text: &mut String, raw: &str) {
+    text.extend(decode_html_entities(raw).chars().map(|c| match c {
+        '\u{A0}' => ' ',
+        c => c,
+    }));
+}
+
+/// Returns what follows the first `end` in `s`, or nothing when it isn't closed.
+fn after<'a>(s: &'a str, end: &str) -> &'a str {
+    match s.find(end) {
+        Some(i) => &s[i + end.len()..],
+        None => "",
+    }
+}
+
+/// Skips to the end tag of a script or style element, matched case-insensitively.
+fn skip_raw_text<'a>(s: &'a str, name: &str) -> &'a str {
+    let mut rest = s;
+    while let Some(i) = rest.find("</") {
+        rest = &rest[i + 2..];
+        let candidate = rest.get(..name.len()).unwrap_or_default();
+        if candidate.eq_ignore_ascii_case(name) {
+            return after(rest, ">");
+        }
+    }
+    ""
+}
+
+struct Tag {
+    /// Lower-cased element name without any namespace prefix.
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    values: Vec<String>,
+    /// Length of the tag in the source, `<` and `>` included.
+    len: usize,
+}
+
+impl Tag {
+    /// Parses a start or end tag at the beginning of `s`.
+    fn parse(s: &str) -> Option<Tag> {
+        let bytes = s.as_bytes();
+        let mut pos = 1;
+        let closing = bytes.get(pos) == Some(&b'/');
+        if closing {
+            pos += 1;
+        }
+        if !bytes.get(pos)?.is_ascii_alphabetic() {
+            return None;
+        }
+        let name_start = pos;
+        while pos < bytes.len() && !is_tag_delimiter(bytes[pos]) {
+            pos += 1;
+        }
+        let qualified = s[name_start..pos].to_ascii_lowercase();
+        let name = match qualified.rsplit_once(':') {
+            Some((_, local)) => local.to_string(),
+            None => qualified,
+        };
+
+        let mut values = Vec::new();
+        let mut self_closing = false;
+        loop {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            match bytes.get(pos) {
+                None => return None,
+                Some(b'>') => break,
+                Some(b'/') => {
+                    self_closing = true;
+                    pos += 1;
+                    continue;
+                }
+                Some(_) => (),
+            }
+            // Attribute name, then an optional `= value`.
+            while pos < bytes.len() && !is_tag_delimiter(bytes[pos]) && bytes[pos] != b'=' {
+                pos += 1;
+            }
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if bytes.get(pos) != Some(&b'=') {
+                continue;
+            }
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            match bytes.get(pos) {
+                Some(quote @ (b'"' | b'\'')) => {
+                    let start = pos + 1;
+                    let end = start + s[start..].find(*quote as char)?;
+                    values.push(s[start..end].to_string());
+                    pos = end + 1;
+                }
+                Some(_) => {
+                    let start = pos;
+                    while pos < bytes.len()
+                        && !bytes[pos].is_ascii_whitespace()
+                        && bytes[pos] != b'>'
+                    {
+                        pos += 1;
+                    }
+                    values.push(s[start..pos].to_string());
+                }
+                None => return None,
+            }
+        }
+
+        Some(Tag {
+            name,
+            closing,
+            self_closing,
+            values,
+            len: pos + 1,
+        })
+    }
+}
+
+fn is_tag_delimiter(b: u8) -> bool {
+    b.is_ascii_whitespace() || b == b'>' || b == b'/'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract, html_to_text};
+
+    #[test]
+    fn tags() {
+        assert_eq!(
+            html_to_text("<p>Hello</p><p>World</p>"),
+            "\nHello\n\nWorld\n"
+        );
+        assert_eq!(html_to_text("SSN 123-<b>45</b>-6789"), "SSN 123-45-6789");
+        assert_eq!(html_to_text("<w:p><w:t>x</w:t></w:p>"), "\n\nx\n\n");
+        assert_eq!(html_to_text("a<br/>b<BR >c"), "a\nb\nc");
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            html_to_text("a&amp;b &lt;c&gt; caf&eacute; &#8211; 1&nbsp;2 &#x41;"),
+            "a&b <c> caf\u{e9} \u{2013} 1 2 A"
+        );
+        assert_eq!(html_to_text("&unknown; &amp"), "&unknown; &amp");
+    }
+
+    #[test]
+    fn skipped_content() {
+        assert_eq!(
+            html_to_text("<script>var ssn = '123-45-6789';</SCRIPT><style>p {}</style>text"),
+            "\n\ntext"
+        );
+        assert_eq!(
+            html_to_text(
+                "<?xml version='1.0'?><!DOCTYPE r><!-- 123-45-6789 --><r><![CDATA[a<b>]]></r>"
+            ),
+            "\na<b>\n"
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let source =
+            r#"<a href="mailto:jane@example.com" title='x &amp; y' data-n=5 hidden>link</a>"#;
+        let segments = extract(source, true);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "link");
+        assert_eq!(segments[1].location.as_deref(), Some("attribute values"));
+        assert_eq!(segments[1].text, "mailto:jane@example.com\nx & y\n5\n");
+        assert_eq!(extract(source, false).len(), 1);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(html_to_text("a < b"), "a < b");
+        assert_eq!(html_to_text("a <p"), "a <p");
+        assert_eq!(html_to_text("</>"), "</>");
+        assert_eq!(html_to_text(r#"<a href="x>y"#), r#"<a href="x>y"#);
+        assert_eq!(html_to_text("a<!-- unclosed"), "a");
+        assert_eq!(html_to_text("a<![CDATA[b"), "ab");
+        assert_eq!(html_to_text("<script>never closed"), "\n");
+        assert_eq!(html_to_text("<\u{e9}>"), "<\u{e9}>");
+        assert_eq!(html_to_text("<p\u{e9} a=\u{e9}>x"), "\nx");
+    }
+}
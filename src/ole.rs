@@ -0,0 +1,131 @@
+use cfb::CompoundFile;
+use encoding_rs::WINDOWS_1252;
+use std::io::{Cursor, Read, Seek};
+
+use crate::container;
+use crate::file_handler::{scan_document, Document, Format, ScanContext};
+
+/// Stream holding a file embedded with the Packager, prefixed by its original name.
+pub const OLE10_NATIVE: &str = "/\u{1}Ole10Native";
+/// Stream holding an embedded OOXML package as is.
+pub const PACKAGE: &str = "/Package";
+/// Stream holding the native data of other embedded documents, such as PDFs.
+pub const CONTENTS: &str = "/CONTENTS";
+
+/// Unwraps the file inside an OLE object, e.g. `oleObject1.bin` in an Office document, and
+/// scans it in place of the object. The name of a packaged file is kept as a note.
+pub fn scan_ole_object(doc: &Document, ctx: &mut ScanContext) {
+    let mut comp = match CompoundFile::open(Cursor::new(doc.data)) {
+        Ok(c) => c,
+        Err(e) => {
+            log::error!("failed to open OLE object {}: {}", doc.path, e);
+            return;
+        }
+    };
+    let (name, data) = if comp.is_stream(OLE10_NATIVE) {
+        match read_stream(&mut comp, OLE10_NATIVE).and_then(|s| ole10_native(&s)) {
+            Some((name, data)) => (Some(name), data),
+            None => return,
+        }
+    } else {
+        let stream = match comp.is_stream(PACKAGE) {
+            true => PACKAGE,
+            false => CONTENTS,
+        };
+        match read_stream(&mut comp, stream) {
+            Some(data) => (None, data),
+            None => return,
+        }
+    };
+
+    if !container::can_open(doc, ctx) {
+        return;
+    }
+    if data.len() as u64 > ctx.remaining_bytes {
+        log::warn!(
+            "stopped unpacking {}: container size limit of {} bytes reached",
+            doc.path,
+            ctx.settings.max_container_bytes
+        );
+        return;
+    }
+    ctx.remaining_bytes -= data.len() as u64;
+
+    let hint = name
+        .as_deref()
+        .and_then(|n| Format::from_name(n, &ctx.settings.extensions));
+    match Document::detect(
+        doc.path.clone(),
+        doc.name.clone(),
+        hint,
+        &data,
+        doc.depth + 1,
+        ctx.settings,
+    ) {
+        Some(mut inner) => {
+            inner
+                .notes
+                .extend(name.map(|n| format!("packaged file: {}", n)));
+            scan_document(&inner, ctx);
+        }
+        None => {
+            if ctx.settings.verbose {
+                println!("Did not scan: {}", doc.path)
+            }
+        }
+    }
+}
+
+/// Splits an `Ole10Native` stream into the original file name and its data. The layout is
+/// a u32 size, u16 flags, the label, the original path, two u16s, the temporary path and
+/// finally the u32 length of the data followed by the data itself.
+fn ole10_native(stream: &[u8]) -> Option<(String, Vec<u8>)> {
+    let mut pos = 6;
+    let _label = c_string(stream, &mut pos)?;
+    let path = c_string(stream, &mut pos)?;
+    pos += 4;
+    let temp_len = read_u32(stream, pos)? as usize;
+    pos += 4 + temp_len;
+    let data_len = read_u32(stream, pos)? as usize;
+    pos += 4;
+    let data = stream.get(pos..pos.checked_add(data_len)?)?;
+
+    let name = path
+        .rsplit(['\\', '/'])
+        .next()
+        .filter(|n| !n.is_empty())
+        .unwrap_or("package")
+        .to_string();
+    Some((name, data.to_vec()))
+}
+
+fn c_string(data: &[u8], pos: &mut usize) -> Option<String> {
+    let rest = data.get(*pos..)?;
+    let end = rest.iter().position(|b| *b == 0)?;
+    *pos += end + 1;
+    Some(
+        WINDOWS_1252
+            .decode_without_bom_handling(&rest[..end])
+            .0
+            .into_owned(),
+    )
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_stream<F: Read + Seek>(comp: &mut CompoundFile<F>, name: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    match comp
+        .open_stream(name)
+        .and_then(|mut s| s.read_to_end(&mut data))
+    {
+        Ok(_) => Some(data),
+        Err(e) => {
+            log::error!("failed to read OLE stream {}: {}", name, e);
+            None
+        }
+    }
+}
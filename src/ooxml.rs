@@ -0,0 +1,501 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::container::{self, PartLimits};
+use crate::file_handler::Segment;
+use crate::markup;
+
+/// An OOXML package, remembering which parts have been read.
+struct Package<'a> {
+    archive: zip::ZipArchive<Cursor<&'a [u8]>>,
+    limits: PartLimits,
+    read: HashSet<String>,
+}
+
+/// Extracts a Word or PowerPoint package part by part, each as a labelled segment such as
+/// `body`, `header`, `comment by J. Doe`, `deleted text by J. Doe` or `slide 12 notes`.
+/// Any other xml part, such as a chart, a diagram or custom XML, is read as markup and
+/// located by its name.
+pub fn extract(data: &[u8], limits: PartLimits) -> Option<Vec<Segment>> {
+    let archive = match zip::ZipArchive::new(Cursor::new(data)) {
+        Ok(z) => z,
+        Err(e) => {
+            log::error!("failed to open OOXML package: {}", e);
+            return None;
+        }
+    };
+    let names: Vec<String> = archive.file_names().map(String::from).collect();
+    let mut package = Package {
+        archive,
+        limits,
+        read: HashSet::new(),
+    };
+
+    let mut segments = Vec::new();
+    if names.iter().any(|n| n == "ppt/presentation.xml") {
+        presentation(&mut package, &names, &mut segments);
+    } else {
+        word(&mut package, &names, &mut segments);
+    }
+    properties(&mut package, &mut segments);
+
+    for name in &names {
+        if !name.ends_with(".xml") || name == "[Content_Types].xml" || package.read.contains(name) {
+            continue;
+        }
+        if let Some(xml) = read_part(&mut package, name) {
+            let text = markup::html_to_text(&String::from_utf8_lossy(&xml));
+            push(&mut segments, name.clone(), text);
+        }
+    }
+
+    Some(segments)
+}
+
+fn word(package: &mut Package, names: &[String], segments: &mut Vec<Segment>) {
+    let mut parts: Vec<(&str, &str)> = vec![("word/document.xml", "body")];
+    for name in names {
+        let label = match part_stem(name, "word/") {
+            Some(s) if s.starts_with("header") => "header",
+            Some(s) if s.starts_with("footer") => "footer",
+            Some("footnotes") => "footnotes",
+            Some("endnotes") => "endnotes",
+            _ => continue,
+        };
+        parts.push((name, label));
+    }
+
+    for (name, label) in parts {
+        let xml = match read_part(package, name) {
+            Some(x) => x,
+            None => continue,
+        };
+        let part = word_text(&xml);
+        push(segments, label.to_string(), part.text);
+        for revision in part.revisions {
+            push(segments, revision.label(), revision.text);
+        }
+    }
+
+    if let Some(xml) = read_part(package, "word/comments.xml") {
+        for comment in word_comments(&xml) {
+            push(segments, comment.label(), comment.text);
+        }
+    }
+}
+
+/// Text of a Word story with the content of tracked insertions and deletions kept apart.
+struct WordText {
+    text: String,
+    revisions: Vec<Authored>,
+}
+
+/// A run of text attributed to its author, such as a comment or a tracked change.
+struct Authored {
+    kind: &'static str,
+    author: Option<String>,
+    text: String,
+}
+
+impl Authored {
+    fn label(&self) -> String {
+        match &self.author {
+            Some(author) => format!("{} by {}", self.kind, author),
+            None => self.kind.to_string(),
+        }
+    }
+}
+
+fn word_text(xml: &[u8]) -> WordText {
+    let mut text = String::new();
+    let mut revisions: Vec<Authored> = Vec::new();
+    let mut open_revisions: Vec<Authored> = Vec::new();
+    let mut in_text = false;
+
+    for event in EventReader::new(xml) {
+        match event {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) if name.prefix.as_deref() == Some("w") => match name.local_name.as_str() {
+                "t" | "delText" => in_text = true,
+                kind @ ("ins" | "del") => open_revisions.push(Authored {
+                    kind: match kind {
+                        "ins" => "inserted text",
+                        _ => "deleted text",
+                    },
+                    author: attribute(&attributes, "author"),
+                    text: String::new(),
+                }),
+                "tab" => current(&mut open_revisions, &mut text).push('\t'),
+                "br" | "cr" => current(&mut open_revisions, &mut text).push('\n'),
+                _ => (),
+            },
+            Ok(XmlEvent::EndElement { name }) if name.prefix.as_deref() == Some("w") => {
+                match name.local_name.as_str() {
+                    "t" | "delText" => in_text = false,
+                    "ins" | "del" => revisions.extend(open_revisions.pop()),
+                    "p" => current(&mut open_revisions, &mut text).push('\n'),
+                    _ => (),
+                }
+            }
+            Ok(XmlEvent::Characters(c)) | Ok(XmlEvent::Whitespace(c)) if in_text => {
+                current(&mut open_revisions, &mut text).push_str(&c);
+            }
+            Err(e) => {
+                log::error!("failed to parse Word xml: {}", e);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    revisions.retain(|r| !r.text.trim().is_empty());
+    WordText { text, revisions }
+}
+
+/// Text goes to the innermost open revision, or to the story itself outside of one.
+fn current<'a>(open_revisions: &'a mut [Authored], text: &'a mut String) -> &'a mut String {
+    match open_revisions.last_mut() {
+        Some(revision) => &mut revision.text,
+        None => text,
+    }
+}
+
+fn word_comments(xml: &[u8]) -> Vec<Authored> {
+    let mut comments = Vec::new();
+    let mut comment: Option<Authored> = None;
+    let mut in_text = false;
+
+    for event in EventReader::new(xml) {
+        match event {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) if name.prefix.as_deref() == Some("w") => match name.local_name.as_str() {
+                "comment" => {
+                    comment = Some(Authored {
+                        kind: "comment",
+                        author: attribute(&attributes, "author"),
+                        text: String::new(),
+                    })
+                }
+                "t" => in_text = true,
+                _ => (),
+            },
+            Ok(XmlEvent::EndElement { name }) if name.prefix.as_deref() == Some("w") => {
+                match name.local_name.as_str() {
+                    "comment" => comments.extend(comment.take()),
+                    "t" => in_text = false,
+                    "p" => {
+                        if let Some(c) = comment.as_mut() {
+                            c.text.push('\n');
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Ok(XmlEvent::Characters(c)) | Ok(XmlEvent::Whitespace(c)) if in_text => {
+                if let Some(comment) = comment.as_mut() {
+                    comment.text.push_str(&c);
+                }
+            }
+            Err(e) => {
+                log::error!("failed to parse Word comments: {}", e);
+                break;
+            }
+            _ => (),
+        }
+    }
+    comments
+}
+
+fn presentation(package: &mut Package, names: &[String], segments: &mut Vec<Segment>) {
+    let authors = read_part(package, "ppt/commentAuthors.xml")
+        .into_iter()
+        .chain(read_part(package, "ppt/authors.xml"))
+        .flat_map(|xml| comment_authors(&xml))
+        .collect::<HashMap<_, _>>();
+
+    for (index, slide) in slide_order(package, names).iter().enumerate() {
+        let number = index + 1;
+        if let Some(xml) = read_part(package, slide) {
+            push(segments, format!("slide {}", number), drawing_text(&xml));
+        }
+
+        // Notes and comments are found through the slide's relationships.
+        let rels = match slide.rsplit_once('/') {
+            Some((dir, file)) => format!("{}/_rels/{}.rels", dir, file),
+            None => continue,
+        };
+        let mut targets: Vec<String> = relationships(package, &rels).into_values().collect();
+        targets.sort();
+        for target in targets {
+            let target = resolve(slide, &target);
+            let xml = match read_part(package, &target) {
+                Some(x) => x,
+                None => continue,
+            };
+            if target.starts_with("ppt/notesSlides/") {
+                push(
+                    segments,
+                    format!("slide {} notes", number),
+                    drawing_text(&xml),
+                );
+            } else if target.starts_with("ppt/comments/") {
+                for comment in slide_comments(&xml, &authors) {
+                    push(
+                        segments,
+                        format!("slide {} {}", number, comment.label()),
+                        comment.text,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Slide parts in presentation order, falling back to their file numbers when the
+/// presentation part can't be read.
+fn slide_order(package: &mut Package, names: &[String]) -> Vec<String> {
+    let rels = relationships(package, "ppt/_rels/presentation.xml.rels");
+    let mut slides = Vec::new();
+    if let Some(xml) = read_part(package, "ppt/presentation.xml") {
+        for event in EventReader::new(xml.as_slice()).into_iter().flatten() {
+            if let XmlEvent::StartElement {
+                name, attributes, ..
+            } = event
+            {
+                if name.local_name != "sldId" {
+                    continue;
+                }
+                let target = attributes
+                    .iter()
+                    .find(|a| a.name.prefix.as_deref() == Some("r") && a.name.local_name == "id")
+                    .and_then(|a| rels.get(&a.value));
+                if let Some(target) = target {
+                    slides.push(resolve("ppt/presentation.xml", target));
+                }
+            }
+        }
+    }
+    if slides.is_empty() {
+        slides = names
+            .iter()
+            .filter(|n| part_stem(n, "ppt/slides/").is_some())
+            .cloned()
+            .collect();
+        slides.sort_by_key(|n| trailing_number(n));
+    }
+    slides
+}
+
+/// Text of a slide or notes page, one line per paragraph.
+fn drawing_text(xml: &[u8]) -> String {
+    let mut text = String::new();
+    let mut in_text = false;
+    for event in EventReader::new(xml) {
+        match event {
+            Ok(XmlEvent::StartElement { name, .. }) if name.prefix.as_deref() == Some("a") => {
+                match name.local_name.as_str() {
+                    "t" => in_text = true,
+                    "br" => text.push('\n'),
+                    "tab" => text.push('\t'),
+                    _ => (),
+                }
+            }
+            Ok(XmlEvent::EndElement { name }) if name.prefix.as_deref() == Some("a") => {
+                match name.local_name.as_str() {
+                    "t" => in_text = false,
+                    "p" => text.push('\n'),
+                    _ => (),
+                }
+            }
+            Ok(XmlEvent::Characters(c)) | Ok(XmlEvent::Whitespace(c)) if in_text => {
+                text.push_str(&c)
+            }
+            Err(e) => {
+                log::error!("failed to parse slide xml: {}", e);
+                break;
+            }
+            _ => (),
+        }
+    }
+    text
+}
+
+/// Maps comment author ids to names, for both legacy and modern PowerPoint comments.
+fn comment_authors(xml: &[u8]) -> Vec<(String, String)> {
+    EventReader::new(xml)
+        .into_iter()
+        .flatten()
+        .filter_map(|event| match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } if name.local_name == "cmAuthor" || name.local_name == "author" => Some((
+                attribute(&attributes, "id")?,
+                attribute(&attributes, "name")?,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn slide_comments(xml: &[u8], authors: &HashMap<String, String>) -> Vec<Authored> {
+    let mut comments = Vec::new();
+    let mut comment: Option<Authored> = None;
+    for event in EventReader::new(xml) {
+        match event {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) if name.local_name == "cm" => {
+                comment = Some(Authored {
+                    kind: "comment",
+                    author: attribute(&attributes, "authorId")
+                        .and_then(|id| authors.get(&id).cloned()),
+                    text: String::new(),
+                })
+            }
+            Ok(XmlEvent::EndElement { name }) if name.local_name == "cm" => {
+                comments.extend(comment.take())
+            }
+            Ok(XmlEvent::EndElement { name }) if name.local_name == "p" => {
+                if let Some(c) = comment.as_mut() {
+                    c.text.push('\n');
+                }
+            }
+            Ok(XmlEvent::Characters(c)) => {
+                if let Some(comment) = comment.as_mut() {
+                    comment.text.push_str(&c);
+                }
+            }
+            Err(e) => {
+                log::error!("failed to parse slide comments: {}", e);
+                break;
+            }
+            _ => (),
+        }
+    }
+    comments
+}
+
+/// Core properties (title, author, keywords...) and custom properties, one `name: value`
+/// line each.
+fn properties(package: &mut Package, segments: &mut Vec<Segment>) {
+    if let Some(xml) = read_part(package, "docProps/core.xml") {
+        let mut text = String::new();
+        let mut element = None;
+        for event in EventReader::new(xml.as_slice()).into_iter().flatten() {
+            match event {
+                XmlEvent::StartElement { name, .. } => element = Some(name.local_name),
+                XmlEvent::Characters(c) => {
+                    if let Some(name) = &element {
+                        text.push_str(&format!("{}: {}\n", name, c));
+                    }
+                }
+                XmlEvent::EndElement { .. } => element = None,
+                _ => (),
+            }
+        }
+        push(segments, "document properties".to_string(), text);
+    }
+
+    if let Some(xml) = read_part(package, "docProps/custom.xml") {
+        let mut text = String::new();
+        for event in EventReader::new(xml.as_slice()).into_iter().flatten() {
+            match event {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } if name.local_name == "property" => {
+                    text.push_str(&attribute(&attributes, "name").unwrap_or_default());
+                    text.push_str(": ");
+                }
+                XmlEvent::Characters(c) => text.push_str(&c),
+                XmlEvent::EndElement { name } if name.local_name == "property" => text.push('\n'),
+                _ => (),
+            }
+        }
+        push(segments, "custom properties".to_string(), text);
+    }
+}
+
+/// Reads a part's relationships as id to target.
+fn relationships(package: &mut Package, rels: &str) -> HashMap<String, String> {
+    let xml = match read_part(package, rels) {
+        Some(x) => x,
+        None => return HashMap::new(),
+    };
+    EventReader::new(xml.as_slice())
+        .into_iter()
+        .flatten()
+        .filter_map(|event| match event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } if name.local_name == "Relationship" => {
+                if attribute(&attributes, "TargetMode").as_deref() == Some("External") {
+                    return None;
+                }
+                Some((
+                    attribute(&attributes, "Id")?,
+                    attribute(&attributes, "Target")?,
+                ))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Resolves a relationship target against the part it belongs to.
+fn resolve(source: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut path: Vec<&str> = source.split('/').collect();
+    path.pop();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                path.pop();
+            }
+            "." => (),
+            s => path.push(s),
+        }
+    }
+    path.join("/")
+}
+
+/// Returns the file stem of an xml part directly inside `dir`, e.g. `header2`.
+fn part_stem<'a>(name: &'a str, dir: &str) -> Option<&'a str> {
+    let file = name.strip_prefix(dir)?;
+    if file.contains('/') {
+        return None;
+    }
+    file.strip_suffix(".xml")
+}
+
+fn trailing_number(name: &str) -> usize {
+    let stem = name.trim_end_matches(".xml");
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().unwrap_or_default()
+}
+
+fn attribute(attributes: &[OwnedAttribute], local_name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|a| a.name.local_name == local_name)
+        .map(|a| a.value.clone())
+}
+
+fn read_part(package: &mut Package, name: &str) -> Option<Vec<u8>> {
+    package.read.insert(name.to_string());
+    container::read_part(&mut package.archive, name, package.limits)
+}
+
+fn push(segments: &mut Vec<Segment>, location: String, text: String) {
+    if !text.trim().is_empty() {
+        segments.push(Segment {
+            location: Some(location),
+            text,
+        });
+    }
+}
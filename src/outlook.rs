@@ -0,0 +1,225 @@
+use cfb::CompoundFile;
+use encoding_rs::WINDOWS_1252;
+use std::io::{self, Cursor, Read, Seek};
+
+use crate::container;
+use crate::file_handler::{record_segments, scan_document, Document, Format, ScanContext, Segment};
+use crate::markup::html_to_text;
+
+// MAPI property tags, see MS-OXPROPS.
+const PR_SUBJECT: u16 = 0x0037;
+const PR_SENDER_NAME: u16 = 0x0C1A;
+const PR_SENDER_EMAIL_ADDRESS: u16 = 0x0C1F;
+const PR_SENDER_SMTP_ADDRESS: u16 = 0x5D01;
+const PR_DISPLAY_BCC: u16 = 0x0E02;
+const PR_BODY: u16 = 0x1000;
+const PR_BODY_HTML: u16 = 0x1013;
+const PR_DISPLAY_NAME: u16 = 0x3001;
+const PR_EMAIL_ADDRESS: u16 = 0x3003;
+const PR_SMTP_ADDRESS: u16 = 0x39FE;
+const PR_ATTACH_DATA: u16 = 0x3701;
+const PR_ATTACH_FILENAME: u16 = 0x3704;
+const PR_ATTACH_LONG_FILENAME: u16 = 0x3707;
+
+// MAPI property types.
+const PT_STRING8: u16 = 0x001E;
+const PT_UNICODE: u16 = 0x001F;
+const PT_BINARY: u16 = 0x0102;
+const PT_OBJECT: u16 = 0x000D;
+
+const RECIPIENT_PREFIX: &str = "__recip_version1.0_";
+const ATTACHMENT_PREFIX: &str = "__attach_version1.0_";
+
+/// Scans an Outlook `.msg` file. Subject, sender, recipients and body are searched as
+/// separate fields, and each attachment is unpacked and scanned as
+/// `mail.msg!/attachment: roster.xlsx`. Embedded messages are scanned the same way.
+pub fn scan_msg(doc: &Document, ctx: &mut ScanContext) {
+    let mut comp = match CompoundFile::open(Cursor::new(doc.data)) {
+        Ok(c) => c,
+        Err(e) => {
+            log::error!("failed to load msg file {}: {}", doc.path, e);
+            return;
+        }
+    };
+    scan_message(doc, &mut comp, "/", "!/", ctx);
+}
+
+/// Scans the message stored under `storage`, which is the root for the `.msg` file itself
+/// and an attachment's data object for embedded messages.
+fn scan_message<F: Read + Seek>(
+    doc: &Document,
+    comp: &mut CompoundFile<F>,
+    storage: &str,
+    separator: &str,
+    ctx: &mut ScanContext,
+) {
+    let mut sender = Vec::new();
+    for tag in [
+        PR_SENDER_NAME,
+        PR_SENDER_SMTP_ADDRESS,
+        PR_SENDER_EMAIL_ADDRESS,
+    ] {
+        if let Some(value) = read_string(comp, storage, tag) {
+            if !sender.contains(&value) {
+                sender.push(value);
+            }
+        }
+    }
+
+    let mut recipients = Vec::new();
+    for recipient in child_storages(comp, storage, RECIPIENT_PREFIX) {
+        let fields: Vec<String> = [PR_DISPLAY_NAME, PR_SMTP_ADDRESS, PR_EMAIL_ADDRESS]
+            .iter()
+            .filter_map(|tag| read_string(comp, &recipient, *tag))
+            .collect();
+        recipients.push(fields.join(" "));
+    }
+    // Bcc recipients are often only kept in the display list.
+    recipients.extend(read_string(comp, storage, PR_DISPLAY_BCC));
+
+    let body = read_string(comp, storage, PR_BODY).or_else(|| {
+        read_stream(comp, &property_path(storage, PR_BODY_HTML, PT_BINARY))
+            .map(|html| html_to_text(&String::from_utf8_lossy(&html)))
+    });
+
+    let fields = [
+        ("subject", read_string(comp, storage, PR_SUBJECT)),
+        ("sender", Some(sender.join(" "))),
+        ("recipients", Some(recipients.join("\n"))),
+        ("body", body),
+    ];
+    let segments = fields
+        .into_iter()
+        .filter_map(|(location, text)| {
+            Some(Segment {
+                location: Some(location.to_string()),
+                text: text?,
+            })
+        })
+        .collect();
+    record_segments(doc, segments, ctx);
+
+    let attachments = child_storages(comp, storage, ATTACHMENT_PREFIX);
+    if attachments.is_empty() || !container::can_open(doc, ctx) {
+        return;
+    }
+    for (i, attachment) in attachments.iter().enumerate() {
+        let name = [PR_ATTACH_LONG_FILENAME, PR_ATTACH_FILENAME, PR_DISPLAY_NAME]
+            .iter()
+            .find_map(|tag| read_string(comp, attachment, *tag))
+            .unwrap_or_else(|| format!("attachment {}", i + 1));
+        let path = format!("{}{}attachment: {}", doc.path, separator, name);
+
+        let embedded = property_path(attachment, PR_ATTACH_DATA, PT_OBJECT);
+        if comp.is_storage(&embedded) {
+            let member = Document {
+                path,
+                name,
+                data: doc.data,
+                format: Format::Msg,
+                hint: None,
+                depth: doc.depth + 1,
+                notes: Vec::new(),
+            };
+            scan_message(&member, comp, &embedded, "/", ctx);
+            continue;
+        }
+
+        let data_path = property_path(attachment, PR_ATTACH_DATA, PT_BINARY);
+        let size = comp.entry(&data_path).map(|e| e.len()).unwrap_or_default();
+        if size > ctx.remaining_bytes {
+            log::warn!(
+                "stopped unpacking {}: container size limit of {} bytes reached",
+                doc.path,
+                ctx.settings.max_container_bytes
+            );
+            return;
+        }
+        let data = match read_stream(comp, &data_path) {
+            Some(d) => d,
+            None => continue,
+        };
+        ctx.remaining_bytes -= data.len() as u64;
+
+        let hint = Format::from_name(&name, &ctx.settings.extensions);
+        match Document::detect(path.clone(), name, hint, &data, doc.depth + 1, ctx.settings) {
+            Some(member) => scan_document(&member, ctx),
+            None => {
+                if ctx.settings.verbose {
+                    println!("Did not scan: {:#}", path)
+                }
+            }
+        }
+    }
+}
+
+/// Lists the recipient or attachment storages directly below `storage`, in order.
+fn child_storages<F: Read + Seek>(
+    comp: &CompoundFile<F>,
+    storage: &str,
+    prefix: &str,
+) -> Vec<String> {
+    let entries = match comp.read_storage(storage) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+    let mut children: Vec<String> = entries
+        .filter(|e| e.is_storage() && e.name().starts_with(prefix))
+        .map(|e| e.path().to_string_lossy().to_string())
+        .collect();
+    children.sort();
+    children
+}
+
+fn property_path(storage: &str, tag: u16, prop_type: u16) -> String {
+    format!(
+        "{}/__substg1.0_{:04X}{:04X}",
+        storage.trim_end_matches('/'),
+        tag,
+        prop_type
+    )
+}
+
+/// Reads a string property, stored either as UTF-16 or in the message code page.
+fn read_string<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+    storage: &str,
+    tag: u16,
+) -> Option<String> {
+    let text = if let Some(data) = read_stream(comp, &property_path(storage, tag, PT_UNICODE)) {
+        let units = data
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect::<String>()
+    } else {
+        let data = read_stream(comp, &property_path(storage, tag, PT_STRING8))?;
+        WINDOWS_1252
+            .decode_without_bom_handling(&data)
+            .0
+            .to_string()
+    };
+    let text = text.trim_end_matches('\0');
+    if text.is_empty() {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+fn read_stream<F: Read + Seek>(comp: &mut CompoundFile<F>, path: &str) -> Option<Vec<u8>> {
+    if !comp.is_stream(path) {
+        return None;
+    }
+    let mut data = Vec::new();
+    let result: io::Result<usize> = comp
+        .open_stream(path)
+        .and_then(|mut s| s.read_to_end(&mut data));
+    match result {
+        Ok(_) => Some(data),
+        Err(e) => {
+            log::error!("failed to read msg property {}: {}", path, e);
+            None
+        }
+    }
+}
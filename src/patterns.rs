@@ -0,0 +1,107 @@
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::rules::Rule;
+
+/// Compile size allowed for the combined set, which grows with the number of patterns.
+const SET_SIZE_LIMIT: usize = 256 * 1024 * 1024;
+
+/// The patterns of the detection rules, plus a `RegexSet` over all of them that tells in a single pass
+/// over a text which patterns can match it. Only those are then run to extract matches.
+///
+/// Key rules, which match key names rather than text, come after the detection rules in the
+/// rule list so findings can point at either by index.
+#[derive(Debug)]
+pub struct Patterns {
+    rules: Vec<Rule>,
+    regexes: Vec<Regex>,
+    key_regexes: Vec<Regex>,
+    set: Option<RegexSet>,
+    /// Matches of each rule rejected by its validator, over the whole run.
+    rejected: Vec<AtomicUsize>,
+}
+
+impl Patterns {
+    /// Compiles the patterns of the rules, failing on the first one that isn't a valid regex.
+    pub fn new(
+        mut rules: Vec<Rule>,
+        key_rules: Vec<Rule>,
+        case_sensitive: bool,
+    ) -> Result<Self, regex::Error> {
+        let build = |rules: &[Rule]| -> Result<Vec<Regex>, regex::Error> {
+            rules
+                .iter()
+                .map(|rule| {
+                    RegexBuilder::new(&rule.pattern)
+                        .case_insensitive(!case_sensitive)
+                        .build()
+                })
+                .collect()
+        };
+        let regexes = build(&rules)?;
+        let key_regexes = build(&key_rules)?;
+        let set = match RegexSetBuilder::new(rules.iter().map(|r| &r.pattern))
+            .case_insensitive(!case_sensitive)
+            .size_limit(SET_SIZE_LIMIT)
+            .dfa_size_limit(SET_SIZE_LIMIT)
+            .build()
+        {
+            Ok(s) => Some(s),
+            Err(e) => {
+                log::warn!("patterns are searched one by one: {}", e);
+                None
+            }
+        };
+        rules.extend(key_rules);
+        let rejected = rules.iter().map(|_| AtomicUsize::new(0)).collect();
+        Ok(Self {
+            rules,
+            regexes,
+            key_regexes,
+            set,
+            rejected,
+        })
+    }
+
+    /// The rule whose pattern has the given index.
+    pub fn rule(&self, index: usize) -> &Rule {
+        &self.rules[index]
+    }
+
+    /// Adds to the number of matches of a rule that its validator rejected.
+    pub fn add_rejected(&self, index: usize, count: usize) {
+        self.rejected[index].fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Number of matches each rule's validator rejected so far, by index, for the rules
+    /// that rejected any.
+    pub fn rejected(&self) -> Vec<(usize, usize)> {
+        self.rejected
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    /// Returns the index of the first key rule that matches a key name.
+    pub fn key_rule(&self, key: &str) -> Option<usize> {
+        let first = self.regexes.len();
+        self.key_regexes
+            .iter()
+            .position(|regex| regex.is_match(key))
+            .map(|i| first + i)
+    }
+
+    /// Returns the patterns that match somewhere in `content`, with their index.
+    pub fn matching<'a>(&'a self, content: &str) -> Vec<(usize, &'a Regex)> {
+        match &self.set {
+            Some(set) => set
+                .matches(content)
+                .into_iter()
+                .map(|index| (index, &self.regexes[index]))
+                .collect(),
+            None => self.regexes.iter().enumerate().collect(),
+        }
+    }
+}
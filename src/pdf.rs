@@ -0,0 +1,339 @@
+use encoding_rs::{UTF_16BE, WINDOWS_1252};
+use flate2::read::ZlibDecoder;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::container;
+use crate::file_handler::{self, ExtractSettings, Format, ScanContext, Segment};
+use crate::markup;
+
+/// Nesting limit for the form field and name trees, which can contain reference cycles.
+const MAX_TREE_DEPTH: usize = 32;
+
+/// Scans a PDF's text, then the files attached to it, reported as `report.pdf!/data.xlsx`.
+/// Returns false when the document can't be loaded as a PDF.
+pub fn scan_pdf(file: &file_handler::Document, ctx: &mut ScanContext) -> bool {
+    let doc = match Document::load_mem(file.data) {
+        Ok(d) => d,
+        Err(e) => {
+            log::error!("failed to load pdf: {}", e);
+            return false;
+        }
+    };
+    file_handler::record_segments(file, extract(&doc, ctx.settings), ctx);
+
+    for (name, stream) in embedded_files(&doc) {
+        if !container::can_open(file, ctx) {
+            break;
+        }
+        let declared_size = stream
+            .dict
+            .get(b"Params")
+            .and_then(Object::as_dict)
+            .and_then(|params| params.get(b"Size"))
+            .and_then(Object::as_i64)
+            .unwrap_or(0);
+        if declared_size.max(0) as u64 > ctx.remaining_bytes {
+            log::warn!(
+                "stopped unpacking {}: container size limit of {} bytes reached",
+                file.path,
+                ctx.settings.max_container_bytes
+            );
+            break;
+        }
+        // The declared size can't be trusted, so the decoding itself is capped as well.
+        let ratio_limit =
+            (stream.content.len() as u64).saturating_mul(ctx.settings.max_container_ratio);
+        let data = match decode_stream(stream, ratio_limit.min(ctx.remaining_bytes)) {
+            Some(d) => d,
+            None => {
+                log::warn!(
+                    "skipped {}!/{}: decoded size exceeds the compression ratio or container size limit",
+                    file.path,
+                    name
+                );
+                continue;
+            }
+        };
+        container::scan_member(
+            file,
+            &name,
+            Format::from_name(&name, &ctx.settings.extensions),
+            &data,
+            ctx,
+        );
+    }
+    true
+}
+
+/// Extracts a PDF page by page, located as `page 3`, followed by its annotations, the
+/// document metadata and the values of filled-in form fields.
+fn extract(doc: &Document, settings: &ExtractSettings) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for (page_number, page_id) in doc.get_pages() {
+        let location = format!("page {}", page_number);
+        match doc.extract_text(&[page_number]) {
+            Ok(text) => segments.push(segment(location.clone(), text)),
+            Err(e) => log::error!("failed to extract text of pdf {}: {}", location, e),
+        }
+
+        let annotations = doc
+            .get_dictionary(page_id)
+            .and_then(|page| page.get_deref(b"Annots", doc))
+            .and_then(Object::as_array)
+            .map(|a| a.as_slice())
+            .unwrap_or_default();
+        for annotation in annotations {
+            let annotation = match doc.dereference(annotation).and_then(|(_, a)| a.as_dict()) {
+                Ok(a) => a,
+                Err(_) => continue,
+            };
+            let text: Vec<String> = [b"T".as_slice(), b"Subj", b"Contents"]
+                .iter()
+                .filter_map(|key| annotation.get_deref(key, doc).ok())
+                .filter_map(text_string)
+                .collect();
+            if !text.is_empty() {
+                segments.push(segment(format!("{} annotation", location), text.join("\n")));
+            }
+        }
+    }
+
+    if let Ok(info) = doc
+        .trailer
+        .get_deref(b"Info", doc)
+        .and_then(Object::as_dict)
+    {
+        let text: Vec<String> = info
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = text_string(doc.dereference(value).ok()?.1)?;
+                Some(format!("{}: {}", String::from_utf8_lossy(key), value))
+            })
+            .collect();
+        segments.push(segment("metadata".to_string(), text.join("\n")));
+    }
+
+    if let Ok(catalog) = doc.catalog() {
+        if let Ok(xmp) = catalog
+            .get_deref(b"Metadata", doc)
+            .and_then(Object::as_stream)
+        {
+            let limit = (xmp.content.len() as u64)
+                .saturating_mul(settings.max_container_ratio)
+                .min(settings.max_decompressed_bytes);
+            match decode_stream(xmp, limit) {
+                Some(content) => {
+                    let text = markup::html_to_text(&String::from_utf8_lossy(&content));
+                    segments.push(segment("XMP metadata".to_string(), text));
+                }
+                None => log::warn!("skipped XMP metadata: decoded size exceeds {} bytes", limit),
+            }
+        }
+
+        if let Ok(fields) = catalog
+            .get_deref(b"AcroForm", doc)
+            .and_then(Object::as_dict)
+            .and_then(|form| form.get_deref(b"Fields", doc))
+            .and_then(Object::as_array)
+        {
+            for field in fields {
+                form_field(doc, field, "", 0, &mut segments);
+            }
+        }
+    }
+
+    segments
+}
+
+/// Collects the files of the document's `EmbeddedFiles` name tree and of file attachment
+/// annotations, with their names. A file listed in both is returned once.
+fn embedded_files(doc: &Document) -> Vec<(String, &Stream)> {
+    let mut specs = Vec::new();
+    if let Ok(tree) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get_deref(b"Names", doc))
+        .and_then(Object::as_dict)
+        .and_then(|names| names.get_deref(b"EmbeddedFiles", doc))
+    {
+        name_tree(doc, tree, 0, &mut specs);
+    }
+    for page_id in doc.get_pages().into_values() {
+        let annotations = doc
+            .get_dictionary(page_id)
+            .and_then(|page| page.get_deref(b"Annots", doc))
+            .and_then(Object::as_array)
+            .map(|a| a.as_slice())
+            .unwrap_or_default();
+        for annotation in annotations {
+            if let Ok(spec) = doc
+                .dereference(annotation)
+                .and_then(|(_, a)| a.as_dict())
+                .and_then(|a| a.get(b"FS"))
+                .and_then(|spec| doc.dereference(spec))
+            {
+                specs.push(spec);
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    specs.retain(|(id, _)| match id {
+        Some(id) => seen.insert(*id),
+        None => true,
+    });
+    specs
+        .into_iter()
+        .filter_map(|(_, spec)| {
+            let spec = spec.as_dict().ok()?;
+            let stream = spec
+                .get_deref(b"EF", doc)
+                .and_then(Object::as_dict)
+                .and_then(|ef| ef.get_deref(b"F", doc))
+                .and_then(Object::as_stream)
+                .ok()?;
+            let name = [b"UF".as_slice(), b"F"]
+                .iter()
+                .find_map(|key| text_string(spec.get_deref(key, doc).ok()?))
+                .unwrap_or_else(|| "attachment".to_string());
+            Some((name, stream))
+        })
+        .collect()
+}
+
+/// Decodes a stream's `FlateDecode` filters, returning `None` when it inflates past `limit`
+/// bytes. Streams with other filters or a predictor are returned as stored, since lopdf can
+/// only decode those in one unbounded piece.
+fn decode_stream(stream: &Stream, limit: u64) -> Option<Vec<u8>> {
+    let filters = stream.filters().unwrap_or_default();
+    let predictor = stream
+        .dict
+        .get(b"DecodeParms")
+        .and_then(Object::as_dict)
+        .and_then(|params| params.get(b"Predictor"))
+        .and_then(Object::as_i64)
+        .unwrap_or(1);
+    let mut data = Cow::Borrowed(stream.content.as_slice());
+    if predictor <= 1 && filters.iter().all(|f| f == "FlateDecode") {
+        for _ in &filters {
+            match container::read_limited(ZlibDecoder::new(data.as_ref()), limit) {
+                Ok(Some(d)) => data = Cow::Owned(d),
+                Ok(None) => return None,
+                Err(e) => {
+                    log::error!("failed to decode pdf stream: {}", e);
+                    data = Cow::Borrowed(stream.content.as_slice());
+                    break;
+                }
+            }
+        }
+    }
+    match data.len() as u64 > limit {
+        true => None,
+        false => Some(data.into_owned()),
+    }
+}
+
+/// Gathers the values of a name tree, whose nodes hold `[key value ...]` pairs in `Names`
+/// or child nodes in `Kids`, along with their object ids.
+fn name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Object,
+    depth: usize,
+    values: &mut Vec<(Option<ObjectId>, &'a Object)>,
+) {
+    if depth >= MAX_TREE_DEPTH {
+        return;
+    }
+    let node = match node.as_dict() {
+        Ok(n) => n,
+        Err(_) => return,
+    };
+    if let Ok(names) = node.get_deref(b"Names", doc).and_then(Object::as_array) {
+        for value in names.iter().skip(1).step_by(2) {
+            if let Ok(value) = doc.dereference(value) {
+                values.push(value);
+            }
+        }
+    }
+    if let Ok(kids) = node.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok((_, kid)) = doc.dereference(kid) {
+                name_tree(doc, kid, depth + 1, values);
+            }
+        }
+    }
+}
+
+/// Records the value of a form field, located by its fully qualified name, then descends
+/// into its children.
+fn form_field(
+    doc: &Document,
+    field: &Object,
+    parent_name: &str,
+    depth: usize,
+    segments: &mut Vec<Segment>,
+) {
+    if depth >= MAX_TREE_DEPTH {
+        return;
+    }
+    let field: &Dictionary = match doc.dereference(field).and_then(|(_, f)| f.as_dict()) {
+        Ok(f) => f,
+        Err(_) => return,
+    };
+    let partial_name = field
+        .get_deref(b"T", doc)
+        .ok()
+        .and_then(text_string)
+        .unwrap_or_default();
+    let name = match (parent_name, partial_name.as_str()) {
+        (parent, "") => parent.to_string(),
+        ("", partial) => partial.to_string(),
+        (parent, partial) => format!("{}.{}", parent, partial),
+    };
+
+    if let Ok(value) = field.get_deref(b"V", doc) {
+        let values: Vec<String> = match value {
+            Object::Array(items) => items
+                .iter()
+                .filter_map(|i| text_string(doc.dereference(i).ok()?.1))
+                .collect(),
+            value => text_string(value).into_iter().collect(),
+        };
+        if !values.is_empty() {
+            segments.push(segment(format!("form field: {}", name), values.join("\n")));
+        }
+    }
+
+    if let Ok(kids) = field.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            form_field(doc, kid, &name, depth + 1, segments);
+        }
+    }
+}
+
+/// Decodes a PDF text string, which is UTF-16BE with a byte order mark, UTF-8 with one, or
+/// PDFDocEncoding. Names are returned as written.
+fn text_string(object: &Object) -> Option<String> {
+    let bytes = match object {
+        Object::String(bytes, _) => bytes.as_slice(),
+        Object::Name(name) => name.as_slice(),
+        _ => return None,
+    };
+    let text = if let Some(utf16) = bytes.strip_prefix(b"\xFE\xFF") {
+        UTF_16BE.decode_without_bom_handling(utf16).0
+    } else if let Some(utf8) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        String::from_utf8_lossy(utf8)
+    } else {
+        // PDFDocEncoding matches Windows-1252 for all printable characters that matter here.
+        WINDOWS_1252.decode_without_bom_handling(bytes).0
+    };
+    Some(text.into_owned())
+}
+
+fn segment(location: String, text: String) -> Segment {
+    Segment {
+        location: Some(location),
+        text,
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use wait_timeout::ChildExt;
+
+use crate::file_handler::{self, Document, FileFindings, ScanContext, Segment};
+use crate::text_encoding;
+
+/// Placeholder in a plugin command that is replaced by the path of the file to extract.
+const PATH_PLACEHOLDER: &str = "{path}";
+
+/// An external command that extracts text from the files it is registered for, such as
+/// `pdftotext {path} -`. Whatever it writes to stdout is searched.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Plugin {
+    pub name: String,
+    /// Lower-case file extensions without the dot, such as `dwg`.
+    pub extensions: Vec<String>,
+    /// MIME types recognised from the content, such as `image/png` or `image/*`.
+    pub mime_types: Vec<String>,
+    /// Program and arguments, split on whitespace. Without a `{path}` argument the file is
+    /// written to the command's stdin.
+    pub command: String,
+    pub timeout_secs: u64,
+}
+
+impl ::std::default::Default for Plugin {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            extensions: Vec::new(),
+            mime_types: Vec::new(),
+            command: String::new(),
+            timeout_secs: 60,
+        }
+    }
+}
+
+/// Finds the first plugin registered for the extension of `name` or the MIME type of
+/// `data`, which may be just the head of the file.
+pub fn find<'a>(name: &str, data: &[u8], plugins: &'a [Plugin]) -> Option<&'a Plugin> {
+    if plugins.is_empty() {
+        return None;
+    }
+    let extension = file_handler::extension(name);
+    let mime_type = infer::get(data).map(|t| t.mime_type());
+    plugins.iter().find(|plugin| {
+        let by_extension = extension
+            .as_ref()
+            .is_some_and(|e| plugin.extensions.iter().any(|p| p.eq_ignore_ascii_case(e)));
+        let by_mime_type = mime_type.is_some_and(|m| {
+            plugin
+                .mime_types
+                .iter()
+                .any(|p| match p.strip_suffix("/*") {
+                    Some(prefix) => m.split('/').next() == Some(prefix),
+                    None => p.eq_ignore_ascii_case(m),
+                })
+        });
+        by_extension || by_mime_type
+    })
+}
+
+/// Runs the plugin registered for a document and searches its output. A failed run is
+/// logged and reported as an extraction error.
+pub fn scan_external(doc: &Document, ctx: &mut ScanContext) {
+    let plugin = match find(&doc.name, doc.data, &ctx.settings.plugins) {
+        Some(p) => p,
+        None => return,
+    };
+    match run(doc, plugin) {
+        Ok(text) => {
+            let segments = vec![Segment {
+                location: None,
+                text,
+            }];
+            file_handler::record_segments(doc, segments, ctx);
+        }
+        Err(e) => {
+            log::error!("plugin {} failed on {}: {}", plugin.name, doc.path, e);
+            let mut notes = doc.notes.clone();
+            notes.push(format!("extraction error: {}", e));
+            ctx.results.push(FileFindings {
+                findings: Vec::new(),
+                match_counts: Vec::new(),
+                filename: doc.name.clone(),
+                path: doc.path.clone(),
+                location: None,
+                notes,
+            });
+        }
+    }
+}
+
+/// Runs a plugin command with a timeout and returns its stdout. Files unpacked from a
+/// container have no path of their own, so they are written to a temporary file first.
+fn run(doc: &Document, plugin: &Plugin) -> Result<String, String> {
+    let mut arguments = plugin.command.split_whitespace();
+    let program = arguments.next().ok_or("no command configured")?;
+
+    let uses_path = plugin.command.contains(PATH_PLACEHOLDER);
+    let mut temp_file = None;
+    let path = match (uses_path, doc.depth) {
+        (false, _) => String::new(),
+        (true, 0) => doc.path.clone(),
+        (true, _) => {
+            let suffix = file_handler::extension(&doc.name)
+                .map(|e| format!(".{}", e))
+                .unwrap_or_default();
+            let mut file = tempfile::Builder::new()
+                .prefix("sift-")
+                .suffix(&suffix)
+                .tempfile()
+                .map_err(|e| format!("failed to create temporary file: {}", e))?;
+            file.write_all(doc.data)
+                .map_err(|e| format!("failed to write temporary file: {}", e))?;
+            let path = file.path().to_string_lossy().to_string();
+            temp_file = Some(file);
+            path
+        }
+    };
+
+    let mut child = Command::new(program)
+        .args(arguments.map(|a| a.replace(PATH_PLACEHOLDER, &path)))
+        .stdin(match uses_path {
+            true => Stdio::null(),
+            false => Stdio::piped(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to start {}: {}", program, e))?;
+
+    // Stdin and stdout are served from their own threads so neither pipe can fill up and
+    // stall the command.
+    let stdin = child.stdin.take().map(|mut stdin| {
+        let data = doc.data.to_vec();
+        thread::spawn(move || {
+            let _ = stdin.write_all(&data);
+        })
+    });
+    let mut stdout = child.stdout.take().ok_or("failed to capture stdout")?;
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+
+    let status = child
+        .wait_timeout(Duration::from_secs(plugin.timeout_secs))
+        .map_err(|e| e.to_string())?;
+    let status = match status {
+        Some(s) => s,
+        None => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("timed out after {} seconds", plugin.timeout_secs));
+        }
+    };
+    if let Some(stdin) = stdin {
+        let _ = stdin.join();
+    }
+    let output = match reader.join() {
+        Ok(Ok(o)) => o,
+        Ok(Err(e)) => return Err(format!("failed to read output: {}", e)),
+        Err(_) => return Err("failed to read output".to_string()),
+    };
+    drop(temp_file);
+
+    if !status.success() {
+        return Err(format!("exited with {}", status));
+    }
+    Ok(text_encoding::decode(&output).0)
+}
@@ -0,0 +1,390 @@
+use encoding_rs::Encoding;
+use std::collections::HashMap;
+
+/// Destinations whose content is formatting data or binary payload rather than text.
+const SKIPPED_DESTINATIONS: [&str; 17] = [
+    "colortbl",
+    "stylesheet",
+    "listtable",
+    "listoverridetable",
+    "revtbl",
+    "rsidtbl",
+    "generator",
+    "pict",
+    "objdata",
+    "themedata",
+    "colorschememapping",
+    "datastore",
+    "latentstyles",
+    "xmlnstbl",
+    "fldinst",
+    "filetbl",
+    "private",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Destination {
+    Text,
+    FontTable,
+    Skip,
+}
+
+#[derive(Clone, Copy)]
+struct GroupState {
+    destination: Destination,
+    /// Number of fallback characters that follow a `\uN` escape.
+    uc: usize,
+    encoding: &'static Encoding,
+}
+
+/// Converts an RTF document to plain text.
+///
+/// Control words and non-text destinations such as `\fonttbl` and `\pict` are dropped,
+/// `\'hh` escapes are decoded with the code page of the active font (or `\ansicpg`), and
+/// `\uN` escapes are decoded as UTF-16 with their fallback characters skipped.
+pub fn extract(data: &[u8]) -> Option<String> {
+    if !data.starts_with(b"{\\rtf") {
+        log::error!("ERROR processing rtf: missing {{\\rtf header");
+        return None;
+    }
+    let mut parser = Parser::new(data);
+    parser.run();
+    Some(parser.text)
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    text: String,
+    state: GroupState,
+    stack: Vec<GroupState>,
+    /// `\'hh` bytes waiting to be decoded together, needed for multi-byte code pages.
+    pending_bytes: Vec<u8>,
+    high_surrogate: Option<u16>,
+    skip_chars: usize,
+    default_encoding: &'static Encoding,
+    fonts: HashMap<i32, &'static Encoding>,
+    current_font: Option<i32>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            text: String::new(),
+            state: GroupState {
+                destination: Destination::Text,
+                uc: 1,
+                encoding: encoding_rs::WINDOWS_1252,
+            },
+            stack: Vec::new(),
+            pending_bytes: Vec::new(),
+            high_surrogate: None,
+            skip_chars: 0,
+            default_encoding: encoding_rs::WINDOWS_1252,
+            fonts: HashMap::new(),
+            current_font: None,
+        }
+    }
+
+    fn run(&mut self) {
+        while self.pos < self.data.len() {
+            let byte = self.data[self.pos];
+            self.pos += 1;
+            match byte {
+                b'{' => {
+                    self.flush_bytes();
+                    self.stack.push(self.state);
+                }
+                b'}' => {
+                    self.flush_bytes();
+                    if let Some(state) = self.stack.pop() {
+                        self.state = state;
+                    }
+                    self.skip_chars = 0;
+                }
+                b'\\' => self.control(),
+                b'\r' | b'\n' => (),
+                b if b < 0x80 => self.push_char(b as char),
+                b => self.push_byte(b),
+            }
+        }
+        self.flush_bytes();
+    }
+
+    fn control(&mut self) {
+        let start = self.pos;
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_alphabetic() {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            self.control_symbol();
+            return;
+        }
+        let word = String::from_utf8_lossy(&self.data[start..self.pos]).to_string();
+
+        let param_start = self.pos;
+        if self.pos < self.data.len() && self.data[self.pos] == b'-' {
+            self.pos += 1;
+        }
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        let param: Option<i32> = std::str::from_utf8(&self.data[param_start..self.pos])
+            .ok()
+            .and_then(|p| p.parse().ok());
+        if self.pos < self.data.len() && self.data[self.pos] == b' ' {
+            self.pos += 1;
+        }
+
+        self.control_word(&word, param);
+    }
+
+    fn control_symbol(&mut self) {
+        let symbol = match self.data.get(self.pos) {
+            Some(s) => *s,
+            None => return,
+        };
+        self.pos += 1;
+        match symbol {
+            b'\'' => {
+                let hex = self.data.get(self.pos..self.pos + 2).unwrap_or_default();
+                self.pos += hex.len();
+                if let Ok(byte) = u8::from_str_radix(&String::from_utf8_lossy(hex), 16) {
+                    self.push_byte(byte);
+                }
+            }
+            b'*' => self.state.destination = Destination::Skip,
+            b'~' => self.push_char('\u{A0}'),
+            b'_' => self.push_char('-'),
+            b'-' => (),
+            b'\r' | b'\n' => self.push_char('\n'),
+            s => self.push_char(s as char),
+        }
+    }
+
+    fn control_word(&mut self, word: &str, param: Option<i32>) {
+        if word == "bin" {
+            // Raw binary data follows; it's never text.
+            self.pos += param.unwrap_or(0).max(0) as usize;
+            return;
+        }
+        if self.state.destination == Destination::Skip {
+            return;
+        }
+        self.flush_bytes();
+
+        if self.state.destination == Destination::FontTable {
+            match (word, param) {
+                ("f", Some(n)) => self.current_font = Some(n),
+                ("fcharset", Some(charset)) => {
+                    if let (Some(font), Some(encoding)) =
+                        (self.current_font, charset_encoding(charset))
+                    {
+                        self.fonts.insert(font, encoding);
+                    }
+                }
+                ("cpg", Some(cp)) => {
+                    if let Some(font) = self.current_font {
+                        self.fonts.insert(font, codepage_encoding(cp));
+                    }
+                }
+                _ => (),
+            }
+            return;
+        }
+
+        match (word, param) {
+            ("fonttbl", _) => self.state.destination = Destination::FontTable,
+            (w, _) if SKIPPED_DESTINATIONS.contains(&w) => {
+                self.state.destination = Destination::Skip
+            }
+            ("ansicpg", Some(cp)) => {
+                self.default_encoding = codepage_encoding(cp);
+                self.state.encoding = self.default_encoding;
+            }
+            ("f", Some(font)) => {
+                self.state.encoding = self
+                    .fonts
+                    .get(&font)
+                    .copied()
+                    .unwrap_or(self.default_encoding);
+            }
+            ("uc", Some(n)) => self.state.uc = n.max(0) as usize,
+            ("u", Some(n)) => {
+                self.push_utf16(n as i16 as u16);
+                self.skip_chars = self.state.uc;
+            }
+            ("par" | "line" | "sect" | "page" | "row", _) => self.push_char('\n'),
+            ("tab" | "cell", _) => self.push_char('\t'),
+            ("emdash", _) => self.push_char('\u{2014}'),
+            ("endash", _) => self.push_char('\u{2013}'),
+            ("bullet", _) => self.push_char('\u{2022}'),
+            ("lquote", _) => self.push_char('\u{2018}'),
+            ("rquote", _) => self.push_char('\u{2019}'),
+            ("ldblquote", _) => self.push_char('\u{201C}'),
+            ("rdblquote", _) => self.push_char('\u{201D}'),
+            _ => (),
+        }
+    }
+
+    fn push_char(&mut self, c: char) {
+        if self.state.destination != Destination::Text {
+            return;
+        }
+        if self.skip_chars > 0 {
+            self.skip_chars -= 1;
+            return;
+        }
+        self.flush_bytes();
+        self.high_surrogate = None;
+        self.text.push(c);
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        if self.state.destination != Destination::Text {
+            return;
+        }
+        if self.skip_chars > 0 {
+            self.skip_chars -= 1;
+            return;
+        }
+        self.pending_bytes.push(byte);
+    }
+
+    fn push_utf16(&mut self, unit: u16) {
+        if self.state.destination != Destination::Text {
+            return;
+        }
+        self.flush_bytes();
+        match (self.high_surrogate.take(), unit) {
+            (None, 0xD800..=0xDBFF) => self.high_surrogate = Some(unit),
+            (Some(high), 0xDC00..=0xDFFF) => {
+                self.text
+                    .extend(char::decode_utf16([high, unit]).filter_map(|c| c.ok()));
+            }
+            (_, unit) => self
+                .text
+                .push(char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER)),
+        }
+    }
+
+    fn flush_bytes(&mut self) {
+        if self.pending_bytes.is_empty() {
+            return;
+        }
+        let (decoded, _) = self
+            .state
+            .encoding
+            .decode_without_bom_handling(&self.pending_bytes);
+        self.text.push_str(&decoded);
+        self.pending_bytes.clear();
+    }
+}
+
+/// Maps a Windows code page number (`\ansicpg`, `\cpg`) to its encoding.
+fn codepage_encoding(codepage: i32) -> &'static Encoding {
+    match codepage {
+        866 => encoding_rs::IBM866,
+        874 => encoding_rs::WINDOWS_874,
+        932 => encoding_rs::SHIFT_JIS,
+        936 => encoding_rs::GBK,
+        949 => encoding_rs::EUC_KR,
+        950 => encoding_rs::BIG5,
+        1250..=1258 => Encoding::for_label(format!("windows-{}", codepage).as_bytes())
+            .unwrap_or(encoding_rs::WINDOWS_1252),
+        10000 => encoding_rs::MACINTOSH,
+        20866 => encoding_rs::KOI8_R,
+        65001 => encoding_rs::UTF_8,
+        _ => encoding_rs::WINDOWS_1252,
+    }
+}
+
+/// Maps a font's `\fcharset` to its encoding.
+fn charset_encoding(charset: i32) -> Option<&'static Encoding> {
+    let codepage = match charset {
+        0 => 1252,
+        77 => 10000,
+        128 => 932,
+        129 => 949,
+        134 => 936,
+        136 => 950,
+        161 => 1253,
+        162 => 1254,
+        163 => 1258,
+        177 => 1255,
+        178 => 1256,
+        186 => 1257,
+        204 => 1251,
+        222 => 874,
+        238 => 1250,
+        _ => return None,
+    };
+    Some(codepage_encoding(codepage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract;
+
+    fn text(rtf: &str) -> String {
+        extract(rtf.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn paragraphs() {
+        assert_eq!(text(r"{\rtf1 Hello\par World}"), "Hello\nWorld");
+        assert_eq!(
+            text("{\\rtf1 SSN\\tab 123-45-6789\r\n}"),
+            "SSN\t123-45-6789"
+        );
+        assert!(extract(b"Hello").is_none());
+    }
+
+    #[test]
+    fn hex_escapes() {
+        assert_eq!(text(r"{\rtf1 caf\'e9}"), "caf\u{e9}");
+        assert_eq!(text(r"{\rtf1\ansi\ansicpg1251 \'c0}"), "\u{410}");
+        assert_eq!(
+            text(r"{\rtf1{\fonttbl{\f1\fcharset204 Arial;}}\f1\'c0}"),
+            "\u{410}"
+        );
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        assert_eq!(text(r"{\rtf1 caf\u233?}"), "caf\u{e9}");
+        assert_eq!(text(r"{\rtf1\uc2\u233??x}"), "\u{e9}x");
+        assert_eq!(text(r"{\rtf1\uc0\u233 x}"), "\u{e9}x");
+        assert_eq!(text(r"{\rtf1 \u-10179?\u-8704?}"), "\u{1F600}");
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(text(r"{\rtf1 {\uc2\u233??}\u233?}"), "\u{e9}\u{e9}");
+        assert_eq!(
+            text(r"{\rtf1{\*\generator Foo;}{\colortbl;\red0;}Text}"),
+            "Text"
+        );
+        assert_eq!(
+            text(r#"{\rtf1{\field{\*\fldinst HYPERLINK "x"}{\fldrslt link}}}"#),
+            "link"
+        );
+        assert_eq!(text(r"{\rtf1{\*\unknown hidden}shown}"), "shown");
+        assert_eq!(text(r"{\rtf1 a\bin3 xyzb}"), "ab");
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(text(r"{\rtf1}}}}x"), "x");
+        assert_eq!(text(r"{\rtf1 {{{a"), "a");
+        assert_eq!(text(r"{\rtf1 a\'4"), "a4");
+        assert_eq!(text(r"{\rtf1 a\'zz"), "a");
+        assert_eq!(text(r"{\rtf1 a\"), "a");
+        assert_eq!(text(r"{\rtf1 a\u"), "a");
+        assert_eq!(text(r"{\rtf1 a\u99999999999?"), "a?");
+        assert_eq!(text(r"{\rtf1 a\bin2147483647"), "a");
+        assert_eq!(text(r"{\rtf1 a\bin-5 b"), "ab");
+    }
+}
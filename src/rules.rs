@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::str::from_utf8;
+
+use crate::encryption;
+use crate::validators::Validator;
+
+/// Prefix of the ids given to rules added as a bare pattern, such as `rule-3`.
+const ID_PREFIX: &str = "rule-";
+/// Prefix of the ids given to key rules added as a bare pattern, such as `key-2`.
+const KEY_ID_PREFIX: &str = "key-";
+
+/// How serious a finding is, from `low` to `critical`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.pad(name)
+    }
+}
+
+/// The kind of data a rule looks for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    #[serde(alias = "PII")]
+    Pii,
+    #[serde(alias = "PCI")]
+    Pci,
+    #[serde(alias = "PHI")]
+    Phi,
+    #[serde(alias = "SECRET")]
+    Secret,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Category::Pii => "PII",
+            Category::Pci => "PCI",
+            Category::Phi => "PHI",
+            Category::Secret => "secret",
+        };
+        f.pad(name)
+    }
+}
+
+/// A named detection rule. Findings are reported under the rule's id and severity.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Rule {
+    /// Stable identifier, such as `us-ssn`.
+    pub id: String,
+    pub name: String,
+    /// The regex searched for.
+    pub pattern: String,
+    pub severity: Severity,
+    /// Left empty for rules migrated from bare patterns.
+    pub category: Option<Category>,
+    pub description: String,
+    /// Check that each match must pass to be reported.
+    pub validator: Option<Validator>,
+    /// The built-in pack the rule comes from, which isn't stored with the rule.
+    #[serde(skip)]
+    pub pack: Option<String>,
+}
+
+impl Rule {
+    /// A rule for a bare pattern, as added with `-a` or kept by older config files. The
+    /// pattern doubles as its name.
+    pub fn from_pattern(id: String, pattern: String) -> Self {
+        Self {
+            id,
+            name: pattern.clone(),
+            pattern,
+            ..Self::default()
+        }
+    }
+
+    /// The id findings are reported under, prefixed by the pack of built-in rules, such as
+    /// `pci/pan`.
+    pub fn label(&self) -> String {
+        match &self.pack {
+            Some(pack) => format!("{}/{}", pack, self.id),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// The `[[rules]]` and `[[key_rules]]` tables of a rules file. Key rules match key names
+/// in JSON, YAML and TOML documents, and every value under a matching key is reported.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct RuleFile {
+    pub rules: Vec<Rule>,
+    pub key_rules: Vec<Rule>,
+}
+
+/// Returns the next free `rule-N` id.
+pub fn next_id(rules: &[Rule]) -> String {
+    next_with_prefix(rules, ID_PREFIX)
+}
+
+/// Returns the next free `key-N` id.
+pub fn next_key_id(key_rules: &[Rule]) -> String {
+    next_with_prefix(key_rules, KEY_ID_PREFIX)
+}
+
+fn next_with_prefix(rules: &[Rule], prefix: &str) -> String {
+    let last = rules
+        .iter()
+        .filter_map(|r| r.id.strip_prefix(prefix)?.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("{}{}", prefix, last + 1)
+}
+
+/// Encrypts a rule for the config file. The whole rule is encrypted, not just its pattern.
+pub fn encrypt(rule: &Rule, password: &str) -> Result<String, Box<dyn Error>> {
+    let json = serde_json::to_string(rule)?;
+    Ok(encryption::encrypt(json.as_bytes(), password))
+}
+
+/// Decrypts the rules stored in the config file.
+pub fn decrypt(encrypted_rules: &[String], password: &str) -> Result<Vec<Rule>, Box<dyn Error>> {
+    let mut rules = Vec::with_capacity(encrypted_rules.len());
+    for encrypted in encrypted_rules {
+        let decrypted_bytes = encryption::decrypt(encrypted, password)?;
+        rules.push(serde_json::from_str(from_utf8(&decrypted_bytes)?)?);
+    }
+    Ok(rules)
+}
+
+/// Reads rules from a TOML file of `[[rules]]` and `[[key_rules]]` tables.
+pub fn load_file(path: &str) -> Result<RuleFile, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    let file: RuleFile = toml::from_str(&contents)?;
+    check(&file.rules)?;
+    check(&file.key_rules)?;
+    Ok(file)
+}
+
+/// Checks that every rule has an id and a pattern that compiles.
+pub fn check(rules: &[Rule]) -> Result<(), Box<dyn Error>> {
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(format!("rule without an id: {}", rule.pattern).into());
+        }
+        if let Err(e) = regex::Regex::new(&rule.pattern) {
+            return Err(format!("invalid pattern in rule {}: {}", rule.id, e).into());
+        }
+    }
+    Ok(())
+}
@@ -2,13 +2,15 @@ use chrono::prelude::*;
 use confy;
 use rpassword;
 use std::error::Error;
-use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::str::from_utf8;
 use std::{env, fs};
 
 use crate::args::Args;
 use crate::encryption;
+use crate::file_handler::{ExtractSettings, Format};
+use crate::packs;
+use crate::rules::{self, Rule};
 use crate::settings::ConfigFile;
 
 use crate::scan_manager::scan_manager;
@@ -21,6 +23,7 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
     }
 
     let mut app_settings: ConfigFile = confy::load("sift", &*config.config_file)?;
+    app_settings.check()?;
 
     let key = "SIFTPW";
     let mut password = match env::var(key) {
@@ -31,9 +34,9 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
         Err(_) => String::new(),
     };
 
-    if config.roots.is_some() {
+    if let Some(roots) = config.roots {
         app_settings.initial_scan = true;
-        for root in config.roots.unwrap() {
+        for root in roots {
             if !app_settings.roots.contains(&root) && Path::new(&root).exists() {
                 println!("adding root: {}\n", root);
                 app_settings.roots.push(root.clone());
@@ -43,8 +46,8 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
         }
     }
 
-    if config.remove_roots.is_some() {
-        for root_to_remove in config.remove_roots.unwrap() {
+    if let Some(remove_roots) = config.remove_roots {
+        for root_to_remove in remove_roots {
             let i = app_settings.roots.iter().position(|r| *r == root_to_remove);
             match i {
                 Some(i) => {
@@ -56,12 +59,18 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
         }
     }
 
+    // Only the flags that need the rules ask for the password.
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut key_rules: Vec<Rule> = Vec::new();
     if config.display_patterns
         || config.scan
         || config.full_scan
         || config.remove_patterns.is_some()
         || config.add_patterns.is_some()
         || config.pattern_file.is_some()
+        || config.rule_file.is_some()
+        || config.add_key_patterns.is_some()
+        || config.remove_key_patterns.is_some()
     {
         if password.is_empty() {
             password = match app_settings.secret {
@@ -84,51 +93,74 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
             eprintln!("\nInvalid password");
             std::process::exit(1);
         }
+
+        rules = rules::decrypt(&app_settings.rules, &password)?;
+        key_rules = rules::decrypt(&app_settings.key_rules, &password)?;
+        if !app_settings.keywords.is_empty() {
+            migrate_keywords(&mut app_settings, &mut rules, &password)?;
+        }
     }
 
-    if config.add_patterns.is_some() {
-        let keywords = load_keywords(&app_settings.keywords, &password).unwrap();
-        for word in config.add_patterns.unwrap() {
-            if !keywords.contains(&word) {
-                println!("adding patterns: {}", word);
-                app_settings
-                    .keywords
-                    .push(encryption::encrypt(word.as_bytes(), &password));
+    if let Some(add_patterns) = config.add_patterns {
+        for word in add_patterns {
+            if !rules.iter().any(|r| r.pattern == word) {
+                let rule = Rule::from_pattern(rules::next_id(&rules), word);
+                if let Err(e) = rules::check(std::slice::from_ref(&rule)) {
+                    println!("{}", e);
+                    continue;
+                }
+                println!("adding patterns: {} as {}", rule.pattern, rule.id);
+                app_settings.rules.push(rules::encrypt(&rule, &password)?);
+                rules.push(rule);
                 app_settings.initial_scan = true;
             }
         }
         println!();
     }
 
-    if config.pattern_file.is_some() {
-        let keywords = load_keywords(&app_settings.keywords, &password).unwrap();
-        let mut file = fs::File::open(config.pattern_file.unwrap()).unwrap();
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
-        let patterns: Vec<&str> = contents
-            .split(|c| c == ',' || c == '\n' || c == '\r')
-            .collect();
+    if let Some(pattern_file) = config.pattern_file {
+        let contents = fs::read_to_string(&pattern_file)
+            .map_err(|e| format!("failed to read {}: {}", pattern_file, e))?;
+        let patterns: Vec<&str> = contents.split([',', '\n', '\r']).collect();
         for pattern in patterns {
             let pattern = pattern.to_string();
-            if !keywords.contains(&pattern) && pattern.len() > 0 {
-                println!("adding patterns: {:?}", pattern);
-                app_settings
-                    .keywords
-                    .push(encryption::encrypt(pattern.as_bytes(), &password));
+            if !rules.iter().any(|r| r.pattern == pattern) && !pattern.is_empty() {
+                let rule = Rule::from_pattern(rules::next_id(&rules), pattern);
+                if let Err(e) = rules::check(std::slice::from_ref(&rule)) {
+                    println!("{}", e);
+                    continue;
+                }
+                println!("adding patterns: {:?} as {}", rule.pattern, rule.id);
+                app_settings.rules.push(rules::encrypt(&rule, &password)?);
+                rules.push(rule);
                 app_settings.initial_scan = true;
             }
         }
     }
 
-    if config.remove_patterns.is_some() {
-        let mut keywords = load_keywords(&app_settings.keywords, &password).unwrap();
-        for word in config.remove_patterns.unwrap() {
-            let i = keywords.iter().position(|w| *w == word);
+    if let Some(rule_file) = config.rule_file {
+        let file = rules::load_file(&rule_file)?;
+        if !file.rules.is_empty() || !file.key_rules.is_empty() {
+            app_settings.initial_scan = true;
+        }
+        import_rules(&mut app_settings.rules, &mut rules, file.rules, &password)?;
+        import_rules(
+            &mut app_settings.key_rules,
+            &mut key_rules,
+            file.key_rules,
+            &password,
+        )?;
+        println!();
+    }
+
+    if let Some(remove_patterns) = config.remove_patterns {
+        for word in remove_patterns {
+            let i = rules.iter().position(|r| r.id == word || r.pattern == word);
             match i {
                 Some(i) => {
                     println!("removing patterns: {}", word);
-                    keywords.remove(i);
-                    app_settings.keywords.remove(i);
+                    rules.remove(i);
+                    app_settings.rules.remove(i);
                 }
                 None => println!("Not found: {}", word),
             }
@@ -136,12 +168,105 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
         println!();
     }
 
-    if config.output_directory.is_some() {
-        println!(
-            "changing output directory to: {:?}",
-            config.output_directory.as_ref().unwrap()
-        );
-        app_settings.output_directory = config.output_directory;
+    if let Some(enable_packs) = config.enable_packs {
+        for pack in enable_packs {
+            if packs::get(&pack).is_none() {
+                println!("unknown pack: {}", pack);
+            } else if !app_settings.packs.contains(&pack) {
+                println!("enabling pack: {}", pack);
+                app_settings.packs.push(pack);
+                app_settings.initial_scan = true;
+            }
+        }
+        println!();
+    }
+
+    if let Some(disable_packs) = config.disable_packs {
+        for pack in disable_packs {
+            let i = app_settings.packs.iter().position(|p| *p == pack);
+            match i {
+                Some(i) => {
+                    println!("disabling pack: {}", pack);
+                    app_settings.packs.remove(i);
+                }
+                None => println!("Not enabled: {}", pack),
+            }
+        }
+        println!();
+    }
+
+    if let Some(add_key_patterns) = config.add_key_patterns {
+        for pattern in add_key_patterns {
+            if key_rules.iter().any(|r| r.pattern == pattern) {
+                continue;
+            }
+            let rule = Rule::from_pattern(rules::next_key_id(&key_rules), pattern);
+            if let Err(e) = rules::check(std::slice::from_ref(&rule)) {
+                println!("{}", e);
+                continue;
+            }
+            println!("adding key pattern: {} as {}", rule.pattern, rule.id);
+            app_settings
+                .key_rules
+                .push(rules::encrypt(&rule, &password)?);
+            key_rules.push(rule);
+            app_settings.initial_scan = true;
+        }
+        println!();
+    }
+
+    if let Some(remove_key_patterns) = config.remove_key_patterns {
+        for word in remove_key_patterns {
+            let i = key_rules
+                .iter()
+                .position(|r| r.id == word || r.pattern == word);
+            match i {
+                Some(i) => {
+                    println!("removing key pattern: {}", word);
+                    key_rules.remove(i);
+                    app_settings.key_rules.remove(i);
+                }
+                None => println!("Not found: {}", word),
+            }
+        }
+        println!();
+    }
+
+    if let Some(set_extensions) = config.set_extensions {
+        for mapping in set_extensions {
+            let (extension, extractor) = match mapping.split_once('=') {
+                Some((e, x)) => (e.trim_start_matches('.').to_lowercase(), x),
+                None => {
+                    println!("expected extension=extractor: {}", mapping);
+                    continue;
+                }
+            };
+            match Format::from_key(extractor) {
+                Some(format) => {
+                    println!("mapping extension: {} to {}", extension, format.key());
+                    app_settings.extensions.insert(extension, format);
+                    app_settings.initial_scan = true;
+                }
+                None => println!("unknown extractor: {}", extractor),
+            }
+        }
+        println!();
+    }
+
+    if let Some(remove_extensions) = config.remove_extensions {
+        for extension in remove_extensions {
+            let extension = extension.trim_start_matches('.').to_lowercase();
+            match app_settings.extensions.remove(&extension) {
+                Some(_) => println!("removing extension: {}", extension),
+                None => println!("Not found: {}", extension),
+            }
+        }
+        println!();
+    }
+
+    if let Some(output_directory) = config.output_directory {
+        println!("changing output directory to: {:?}", output_directory);
+        app_settings.output_directory = Some(output_directory);
     }
 
     confy::store("sift", &*config.config_file, &app_settings)?;
@@ -152,12 +277,60 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
         println!("Max scan threads:{:^50}", app_settings.max_scan_threads);
         println!("Max file threads:{:^50}", app_settings.max_file_threads);
         println!("Max write lines:{:^51}", app_settings.max_write_lines);
+        println!(
+            "Max container depth:{:^47}",
+            app_settings.max_container_depth
+        );
+        println!(
+            "Max container ratio:{:^47}",
+            app_settings.max_container_ratio
+        );
+        println!(
+            "Max container bytes:{:^47}",
+            app_settings.max_container_bytes
+        );
+        println!(
+            "Max decompressed bytes:{:^44}",
+            app_settings.max_decompressed_bytes
+        );
+        println!(
+            "Scan markup attributes:{:^44}",
+            app_settings.scan_markup_attributes
+        );
+        println!(
+            "Scan structured keys:{:^46}",
+            app_settings.scan_structured_keys
+        );
+        println!("Column threshold:{:^50}", app_settings.column_threshold);
+        println!("Column sample rows:{:^48}", app_settings.column_sample_rows);
+        println!("Scan strings:{:^54}", app_settings.scan_strings);
+        println!("Strings min length:{:^48}", app_settings.strings_min_length);
+        println!(
+            "Max distinct values:{:^47}",
+            app_settings.max_distinct_values
+        );
         println!("Initial scan:{:^58}", app_settings.initial_scan);
         println!(
             "Output directory:{:^50}",
             app_settings.output_directory.as_ref().unwrap()
         );
         println!("Roots:{:#?}", app_settings.roots);
+        println!("Packs:{:#?}", app_settings.packs);
+        println!("Plugins:");
+        for plugin in &app_settings.plugins {
+            println!(
+                "\t{:<16}{} (extensions: {}, MIME types: {}, timeout: {}s)",
+                plugin.name,
+                plugin.command,
+                plugin.extensions.join(" "),
+                plugin.mime_types.join(" "),
+                plugin.timeout_secs
+            );
+        }
+        println!("Extensions:");
+        for (extension, format) in &app_settings.extensions {
+            println!("\t{:<16}{}", extension, format.key());
+        }
         println!("Last scan:{:^63}", app_settings.time_last_scan);
         println!("\nConfig file path:");
         println!(
@@ -168,14 +341,40 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
         );
     }
 
-    let keywords = load_keywords(&app_settings.keywords, &password).unwrap();
+    if config.list_packs {
+        println!("{:^50}", "_rule packs_");
+        for name in packs::names() {
+            let pack = packs::get(name).unwrap();
+            let enabled = match app_settings.packs.contains(&pack.name) {
+                true => " (enabled)",
+                false => "",
+            };
+            println!("{}{}: {}", pack.name, enabled, pack.description);
+            for rule in &pack.rules {
+                print_rule(rule);
+            }
+            println!();
+        }
+    }
 
     if config.display_patterns {
-        println!("{:^50}", "_keywords_");
-        for (index, keyword) in keywords.iter().enumerate() {
-            println!("{:<1}: {:>5}", index + 1, keyword);
+        println!("{:^50}", "_rules_");
+        for rule in &rules {
+            print_rule(rule);
         }
         println!();
+        println!("{:^50}", "_key rules_");
+        for rule in &key_rules {
+            print_rule(rule);
+        }
+        println!();
+    }
+
+    for name in &app_settings.packs {
+        match packs::get(name) {
+            Some(pack) => rules.extend(pack.rules.iter().cloned()),
+            None => println!("unknown pack: {}", name),
+        }
     }
 
     if !prescan_checks(&app_settings) {
@@ -184,22 +383,37 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
     }
 
     if config.scan || config.full_scan {
-        let full_scan: bool;
-        if app_settings.initial_scan {
+        let full_scan = if app_settings.initial_scan {
             println!("Conducting initial scan.");
-            full_scan = true;
+            true
         } else {
-            full_scan = config.full_scan;
-        }
+            config.full_scan
+        };
         app_settings.initial_scan = false;
         let last_scan_time: DateTime<Utc> = match app_settings.time_last_scan.parse() {
             Ok(t) => t,
             Err(_) => Utc::now(),
         };
+        let extract_settings = ExtractSettings {
+            verbose: config.verbose,
+            max_container_depth: app_settings.max_container_depth,
+            max_container_ratio: app_settings.max_container_ratio,
+            max_container_bytes: app_settings.max_container_bytes,
+            max_decompressed_bytes: app_settings.max_decompressed_bytes,
+            markup_attributes: app_settings.scan_markup_attributes,
+            structured_keys: app_settings.scan_structured_keys,
+            column_threshold: app_settings.column_threshold,
+            column_sample_rows: app_settings.column_sample_rows,
+            strings_fallback: app_settings.scan_strings,
+            strings_min_length: app_settings.strings_min_length,
+            max_distinct_values: app_settings.max_distinct_values,
+            extensions: app_settings.extensions.clone(),
+            plugins: app_settings.plugins.clone(),
+        };
         let scan_settings = ScanSettings::new(
             full_scan,
-            config.verbose,
-            keywords,
+            rules,
+            key_rules,
             app_settings.roots.clone(),
             Some(last_scan_time),
             PathBuf::from(&app_settings.output_directory.as_ref().unwrap()),
@@ -207,6 +421,7 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
             app_settings.max_scan_threads,
             app_settings.max_file_threads,
             app_settings.max_write_lines,
+            extract_settings,
         );
         app_settings.time_last_scan = Utc::now().to_string();
         scan_manager(scan_settings);
@@ -218,6 +433,67 @@ pub fn run(config: Args) -> Result<(), Box<dyn Error>> {
 }
 
 // HELPERS //
+fn print_rule(rule: &Rule) {
+    let category = rule.category.map(|c| c.to_string()).unwrap_or_default();
+    println!(
+        "{:<24}{:<10}{:<8}{}",
+        rule.id, rule.severity, category, rule.name
+    );
+    println!("\t{}", rule.pattern);
+    if let Some(validator) = rule.validator {
+        println!("\tvalidated with {}", validator);
+    }
+    if !rule.description.is_empty() {
+        println!("\t{}", rule.description);
+    }
+}
+
+/// Turns the bare patterns of older config files into rules with generated ids.
+fn migrate_keywords(
+    app_settings: &mut ConfigFile,
+    rules: &mut Vec<Rule>,
+    password: &str,
+) -> Result<(), Box<dyn Error>> {
+    for keyword in load_keywords(&app_settings.keywords, password)? {
+        if rules.iter().any(|r| r.pattern == keyword) {
+            continue;
+        }
+        let rule = Rule::from_pattern(rules::next_id(rules), keyword);
+        println!("migrating pattern: {} to {}", rule.pattern, rule.id);
+        app_settings.rules.push(rules::encrypt(&rule, password)?);
+        rules.push(rule);
+    }
+    app_settings.keywords.clear();
+    println!();
+    Ok(())
+}
+
+/// Adds the rules read from a rules file to `rules`, replacing those with the same id, and
+/// their encrypted copies to `encrypted_rules`.
+fn import_rules(
+    encrypted_rules: &mut Vec<String>,
+    rules: &mut Vec<Rule>,
+    imported: Vec<Rule>,
+    password: &str,
+) -> Result<(), Box<dyn Error>> {
+    for rule in imported {
+        let encrypted = rules::encrypt(&rule, password)?;
+        match rules.iter().position(|r| r.id == rule.id) {
+            Some(i) => {
+                println!("updating rule: {}", rule.id);
+                encrypted_rules[i] = encrypted;
+                rules[i] = rule;
+            }
+            None => {
+                println!("adding rule: {}", rule.id);
+                encrypted_rules.push(encrypted);
+                rules.push(rule);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn load_keywords(
     encrypted_keywords: &Vec<String>,
     password: &str,
@@ -237,8 +513,12 @@ fn prescan_checks(app_settings: &ConfigFile) -> bool {
         println!("!Pre-scan check failed:: No output directory designated.");
         scan_status = false
     }
-    if app_settings.keywords.is_empty() {
-        println!("!Pre-scan check failed:: No keywords designated.");
+    if app_settings.rules.is_empty()
+        && app_settings.keywords.is_empty()
+        && app_settings.packs.is_empty()
+        && app_settings.key_rules.is_empty()
+    {
+        println!("!Pre-scan check failed:: No rules designated.");
         scan_status = false;
     }
     if app_settings.roots.is_empty() {
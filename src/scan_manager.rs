@@ -4,6 +4,7 @@ use std::thread::{self, JoinHandle};
 use std::time::SystemTime;
 
 use crate::csv_writer::writer;
+use crate::patterns::Patterns;
 use crate::scan_settings::ScanSettings;
 use crate::scanner::scan;
 use crate::sift::ScanMessage;
@@ -15,8 +16,6 @@ use log4rs::append::file::FileAppender;
 use log4rs::config::{Appender, Config, Root};
 use log4rs::encode::pattern::PatternEncoder;
 
-use regex::{Regex, RegexBuilder};
-
 pub fn scan_manager(scan_settings: ScanSettings) {
     build_logger(scan_settings.output_dir.clone());
     let last_time_stamp = match scan_settings.last_scan_time_stamp {
@@ -30,34 +29,60 @@ pub fn scan_manager(scan_settings: ScanSettings) {
         None => SystemTime::UNIX_EPOCH,
     };
 
-    let patterns = Arc::new(load_regex(
-        scan_settings.keywords,
+    let patterns = match Patterns::new(
+        scan_settings.rules,
+        scan_settings.key_rules,
         scan_settings.case_sensitive,
-    ));
+    ) {
+        Ok(p) => Arc::new(p),
+        Err(e) => {
+            log::error!("failed to compile patterns: {}", e);
+            eprintln!("Scan not started, failed to compile patterns: {}", e);
+            return;
+        }
+    };
+
+    let extract_settings = Arc::new(scan_settings.extract_settings);
 
     let mut handles: Vec<JoinHandle<()>> = Vec::new();
     for root in scan_settings.roots {
         let output_dir = scan_settings.output_dir.clone();
         let patterns = patterns.clone();
+        let extract_settings = extract_settings.clone();
         // let root_clone = root.clone();
         println!("Starting scan: {}", root);
 
         let handle = thread::spawn(move || {
             let (tx, rx) = unbounded::<ScanMessage>();
             let root_path = PathBuf::from(&root);
-            let dir_walk = WalkDir::new(root_path);
-            writer(output_dir, &root, rx, scan_settings.max_write_lines);
+            // Hidden files such as `.env` are scanned, hidden folders such as `.git` aren't.
+            let dir_walk =
+                WalkDir::new(root_path)
+                    .skip_hidden(false)
+                    .process_read_dir(|_, _, _, children| {
+                        children.retain(|child| match child {
+                            Ok(entry) => {
+                                !(entry.file_type.is_dir()
+                                    && entry.file_name.to_string_lossy().starts_with('.'))
+                            }
+                            Err(_) => true,
+                        });
+                    });
+            let writer_handle = writer(output_dir, &root, rx, scan_settings.max_write_lines);
             match scan(
                 dir_walk,
                 tx.clone(),
                 patterns,
                 last_time_stamp,
-                scan_settings.verbose,
+                extract_settings,
                 scan_settings.max_scan_threads,
             ) {
                 Ok(_) => (),
                 Err(e) => eprintln!("{:?} panic at {}", e, root),
             }
+            if writer_handle.join().is_err() {
+                eprintln!("writer failed for {}", root);
+            }
 
             println!("Scan complete: {root}");
         });
@@ -76,18 +101,20 @@ pub fn scan_manager(scan_settings: ScanSettings) {
     }
 
     println!("Scan(s) completed");
-}
 
-fn load_regex(keywords: Vec<String>, case_sensitive: bool) -> Vec<Regex> {
-    keywords
-        .iter()
-        .map(|kw| {
-            RegexBuilder::new(&kw)
-                .case_insensitive(!case_sensitive)
-                .build()
-                .unwrap()
-        })
-        .collect()
+    let rejected = patterns.rejected();
+    if !rejected.is_empty() {
+        println!("Matches rejected by validators:");
+        for (index, count) in rejected {
+            let rule = patterns.rule(index);
+            println!(
+                "\t{:<32}{} ({})",
+                rule.label(),
+                count,
+                rule.validator.unwrap()
+            );
+        }
+    }
 }
 
 fn build_logger(output_path: PathBuf) {
@@ -2,12 +2,15 @@ use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
 
+use crate::file_handler::ExtractSettings;
+use crate::rules::Rule;
+
 #[derive(Debug)]
 pub struct ScanSettings {
     pub full_scan: bool,
     pub time_stamp: DateTime<Utc>,
-    pub verbose: bool,
-    pub keywords: Vec<String>,
+    pub rules: Vec<Rule>,
+    pub key_rules: Vec<Rule>,
     pub roots: Vec<String>,
     pub last_scan_time_stamp: Option<DateTime<Utc>>,
     pub output_dir: PathBuf,
@@ -15,13 +18,15 @@ pub struct ScanSettings {
     pub max_scan_threads: usize,
     pub max_file_threads: usize,
     pub max_write_lines: u16,
+    pub extract_settings: ExtractSettings,
 }
 
 impl ScanSettings {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         full_scan: bool,
-        verbose: bool,
-        keywords: Vec<String>,
+        rules: Vec<Rule>,
+        key_rules: Vec<Rule>,
         roots: Vec<String>,
         last_scan_time_stamp: Option<DateTime<Utc>>,
         output_dir: PathBuf,
@@ -29,13 +34,14 @@ impl ScanSettings {
         max_scan_threads: usize,
         max_file_threads: usize,
         max_write_lines: u16,
+        extract_settings: ExtractSettings,
     ) -> Self {
         let time_stamp = Utc::now();
         Self {
             full_scan,
             time_stamp,
-            verbose,
-            keywords,
+            rules,
+            key_rules,
             roots,
             last_scan_time_stamp,
             output_dir,
@@ -43,6 +49,7 @@ impl ScanSettings {
             max_scan_threads,
             max_file_threads,
             max_write_lines,
+            extract_settings,
         }
     }
 }
@@ -1,19 +1,20 @@
 use std::thread::JoinHandle;
 use std::{error::Error, sync::Arc, thread, time::SystemTime};
 
+use crate::file_handler::{self, ExtractSettings, Finding};
+use crate::patterns::Patterns;
+use crate::sift::Row;
 use crate::sift::ScanMessage;
 use crate::sift::ScanMessage::{Msg, END};
-use crate::{file_handler, sift::Row};
 use crossbeam::channel::Sender;
 use jwalk::WalkDirGeneric;
-use regex::Regex;
 
 pub fn scan(
     dir_walk: WalkDirGeneric<((), ())>,
     tx: Sender<ScanMessage>,
-    patterns: Arc<Vec<Regex>>,
+    patterns: Arc<Patterns>,
     last_timestamp: SystemTime,
-    verbose: bool,
+    settings: Arc<ExtractSettings>,
     max_file_threads: usize,
 ) -> Result<(), Box<dyn Error>> {
     let mut handles: Vec<JoinHandle<()>> = Vec::new();
@@ -31,6 +32,7 @@ pub fn scan(
             Err(_) => true,
         };
         let patterns = patterns.clone();
+        let settings = settings.clone();
         let current_tx = tx.clone();
 
         if now.elapsed().as_secs() >= 30 {
@@ -39,22 +41,41 @@ pub fn scan(
         }
 
         if scan_file {
-            let file_name = dir_entry.file_name().to_string_lossy().to_string();
-
             let handle = thread::Builder::new()
                 .name(format!("{}", dir_entry.path().to_string_lossy()))
                 .spawn(move || {
-                    let findings = file_handler::scan_file(&path, &patterns, verbose);
+                    let results = file_handler::scan_file(&path, &patterns, &settings);
 
-                    if findings.is_some() {
-                        if verbose {
-                            println!("Findings in {}", file_name);
+                    for result in results {
+                        if settings.verbose {
+                            println!("Findings in {}", result.path);
                         }
-                        let findings = findings_to_string(findings.unwrap().0);
+                        let findings = findings_to_string(&result.findings, &patterns);
+                        let matches = result
+                            .match_counts
+                            .iter()
+                            .map(|(index, count)| {
+                                format!("{}: {}", patterns.rule(*index).label(), count)
+                            })
+                            .collect::<Vec<String>>()
+                            .join(", ");
+                        let severity = result
+                            .findings
+                            .iter()
+                            .map(|f| f.rule)
+                            .chain(result.match_counts.iter().map(|(index, _)| *index))
+                            .map(|index| patterns.rule(index).severity)
+                            .max()
+                            .map(|s| s.to_string())
+                            .unwrap_or_default();
                         match current_tx.send(Msg(Row {
                             findings: findings.clone(),
-                            filename: file_name,
-                            path: path.to_string_lossy().to_string(),
+                            matches,
+                            severity,
+                            filename: result.filename,
+                            path: result.path,
+                            location: result.location.unwrap_or_default(),
+                            notes: result.notes.join("; "),
                         })) {
                             Ok(_) => (),
                             Err(e) => {
@@ -74,10 +95,7 @@ pub fn scan(
     }
     println!("Sending writer termination.");
     for handle in handles {
-        match handle.join() {
-            Ok(_) => (),
-            Err(_) => (),
-        }
+        let _ = handle.join();
     }
     match tx.send(END) {
         Ok(_) => println!("Writer close message sent successfully"),
@@ -87,13 +105,28 @@ pub fn scan(
     Ok(())
 }
 
-fn findings_to_string(findings: Vec<String>) -> String {
-    let mut words = String::new();
-    for word in findings {
-        words.push_str(&word);
-        words.push_str(", ");
+/// Lists the findings under the id and severity of the rule that found them, such as
+/// `pii-us/ssn (high): 123-45-6789; email (low): jane@example.com`. Values reported because of
+/// their key are listed under the key rule.
+fn findings_to_string(findings: &[Finding], patterns: &Patterns) -> String {
+    let mut groups: Vec<(usize, Vec<&str>)> = Vec::new();
+    for finding in findings {
+        match groups.iter_mut().find(|(rule, _)| *rule == finding.rule) {
+            Some((_, values)) => values.push(&finding.value),
+            None => groups.push((finding.rule, vec![&finding.value])),
+        }
     }
-    words.trim().to_string();
-    words.trim_end_matches(",").to_string();
-    words
+    groups
+        .into_iter()
+        .map(|(rule, values)| {
+            let rule = patterns.rule(rule);
+            format!(
+                "{} ({}): {}",
+                rule.label(),
+                rule.severity,
+                values.join(", ")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("; ")
 }
@@ -1,16 +1,45 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use crate::file_handler::Format;
+use crate::plugin::Plugin;
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct ConfigFile {
     pub initial_scan: bool,
     pub output_directory: Option<String>,
+    /// Bare patterns from before rules, moved to `rules` the next time the password is given.
     pub keywords: Vec<String>,
+    /// Detection rules, each encrypted as a whole.
+    pub rules: Vec<String>,
+    /// Names of the built-in rule packs searched for alongside `rules`.
+    pub packs: Vec<String>,
     pub roots: Vec<String>,
     pub secret: Option<String>,
     pub time_last_scan: String,
     pub max_scan_threads: usize,
     pub max_file_threads: usize,
     pub max_write_lines: u16,
+    /// How deeply containers are opened. A compressed file is still decompressed one level
+    /// deeper, so a plain `export.csv.gz` is read with this set to 0.
+    pub max_container_depth: usize,
+    pub max_container_ratio: u64,
+    pub max_container_bytes: u64,
+    pub max_decompressed_bytes: u64,
+    pub scan_markup_attributes: bool,
+    pub scan_structured_keys: bool,
+    pub column_threshold: u8,
+    pub column_sample_rows: usize,
+    /// Rules matching key names in JSON, YAML and TOML documents, each encrypted as a whole.
+    pub key_rules: Vec<String>,
+    pub scan_strings: bool,
+    pub strings_min_length: usize,
+    pub max_distinct_values: usize,
+    // Kept ahead of the extensions table, as TOML can't have an empty list after a table.
+    pub plugins: Vec<Plugin>,
+    pub extensions: BTreeMap<String, Format>,
 }
 
 impl ::std::default::Default for ConfigFile {
@@ -19,12 +48,42 @@ impl ::std::default::Default for ConfigFile {
             initial_scan: true,
             output_directory: None,
             keywords: Vec::new(),
+            rules: Vec::new(),
+            packs: Vec::new(),
             roots: Vec::new(),
             secret: None,
             time_last_scan: String::new(),
             max_scan_threads: 2,
             max_file_threads: 5,
             max_write_lines: 10000,
+            max_container_depth: 3,
+            max_container_ratio: 100,
+            max_container_bytes: 1024 * 1024 * 1024,
+            max_decompressed_bytes: 256 * 1024 * 1024,
+            scan_markup_attributes: false,
+            scan_structured_keys: false,
+            column_threshold: 0,
+            column_sample_rows: 1000,
+            key_rules: Vec::new(),
+            scan_strings: false,
+            strings_min_length: 6,
+            max_distinct_values: 1000,
+            plugins: Vec::new(),
+            extensions: Format::default_extensions(),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Checks the settings that a hand-edited config file can put out of range.
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        if self.column_threshold > 100 {
+            return Err(format!(
+                "column_threshold is a percentage from 0 to 100, not {}",
+                self.column_threshold
+            )
+            .into());
         }
+        Ok(())
     }
 }
@@ -0,0 +1,320 @@
+use cfb::CompoundFile;
+use flate2::read::GzDecoder;
+use std::io::{Cursor, Read};
+
+use crate::file_handler::Format;
+use crate::ole;
+use crate::text_encoding;
+
+/// Number of leading bytes that are enough to tell whether a file is worth reading in full.
+pub const HEAD_LEN: usize = 8192;
+
+const PDF_MAGIC: &[u8] = b"%PDF-";
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const RTF_MAGIC: &[u8] = b"{\\rtf";
+const WPC_MAGIC: &[u8] = b"\xFFWPC";
+const CFB_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const EMPTY_ZIP_MAGIC: &[u8] = b"PK\x05\x06";
+const GZIP_MAGIC: &[u8] = b"\x1F\x8B";
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = b"\xFD7zXZ\x00";
+const ZSTD_MAGIC: &[u8] = b"\x28\xB5\x2F\xFD";
+const TAR_MAGIC: &[u8] = b"ustar";
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Openings of HTML and XML documents.
+const MARKUP_STARTS: [&str; 4] = ["<?xml", "<!doctype html", "<html", "<svg"];
+
+/// Headers that commonly open a stored RFC 5322 message.
+const EMAIL_HEADERS: [&str; 7] = [
+    "return-path",
+    "received",
+    "delivered-to",
+    "message-id",
+    "mime-version",
+    "from",
+    "date",
+];
+
+/// Detects the format of a document from its content.
+///
+/// Given only the first [`HEAD_LEN`] bytes the answer is coarse: a zip package is reported
+/// as [`Format::Zip`] and a compound file as [`Format::LegacyOffice`] until the whole
+/// document is available to look inside.
+pub fn sniff(data: &[u8]) -> Option<Format> {
+    if data.starts_with(ZIP_MAGIC) || data.starts_with(EMPTY_ZIP_MAGIC) {
+        return Some(sniff_zip(data));
+    }
+    if data.starts_with(CFB_MAGIC) {
+        return Some(sniff_cfb(data));
+    }
+    if data.starts_with(RTF_MAGIC) {
+        return Some(Format::Rtf);
+    }
+    if data.starts_with(WPC_MAGIC) {
+        return Some(Format::WordPerfect);
+    }
+    // Only a header at the start counts, so text that merely mentions it stays text.
+    let start = data.strip_prefix(UTF8_BOM).unwrap_or(data);
+    let start = match start.iter().position(|b| !b.is_ascii_whitespace()) {
+        Some(i) => &start[i..],
+        None => &[],
+    };
+    if start.starts_with(PDF_MAGIC) {
+        return Some(Format::Pdf);
+    }
+    if is_tar(data) {
+        return Some(Format::Tar);
+    }
+    if data.starts_with(GZIP_MAGIC) {
+        let mut header = Vec::new();
+        let _ = GzDecoder::new(data)
+            .take(TAR_MAGIC_OFFSET as u64 + 8)
+            .read_to_end(&mut header);
+        return match is_tar(&header) {
+            true => Some(Format::TarGz),
+            false => Some(Format::Gzip),
+        };
+    }
+    if data.starts_with(BZIP2_MAGIC) && data.get(3).is_some_and(|b| (b'1'..=b'9').contains(b)) {
+        return Some(Format::Bzip2);
+    }
+    if data.starts_with(XZ_MAGIC) {
+        return Some(Format::Xz);
+    }
+    if data.starts_with(ZSTD_MAGIC) {
+        return Some(Format::Zstd);
+    }
+    if looks_like_text(data) {
+        return Some(sniff_text(data));
+    }
+    None
+}
+
+/// Settles on a format when both the name and the content may have an opinion. The
+/// content wins a disagreement, which is returned as a note for the report.
+pub fn resolve(hint: Option<Format>, sniffed: Option<Format>) -> Option<(Format, Option<String>)> {
+    match (hint, sniffed) {
+        (None, None) => None,
+        (Some(format), None) | (None, Some(format)) => Some((format, None)),
+        (Some(hint), Some(sniffed)) if hint == sniffed => Some((hint, None)),
+        // Plain text can't be told apart by content, so the name is more specific.
+        (Some(hint), Some(sniffed)) if hint.is_text() && sniffed.is_text() => Some((hint, None)),
+        (Some(hint), Some(sniffed)) => Some((
+            sniffed,
+            Some(format!(
+                "name suggests {} but content is {}",
+                hint.label(),
+                sniffed.label()
+            )),
+        )),
+    }
+}
+
+fn is_tar(data: &[u8]) -> bool {
+    data.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + TAR_MAGIC.len()) == Some(TAR_MAGIC)
+}
+
+/// Tells OOXML and OpenDocument packages apart from plain zip archives by their content
+/// types.
+fn sniff_zip(data: &[u8]) -> Format {
+    let mut archive = match zip::ZipArchive::new(Cursor::new(data)) {
+        Ok(z) => z,
+        Err(_) => return Format::Zip,
+    };
+
+    let mut mimetype = String::new();
+    if let Ok(mut part) = archive.by_name("mimetype") {
+        let _ = (&mut part).take(128).read_to_string(&mut mimetype);
+    }
+    if let Some(kind) = mimetype
+        .trim()
+        .strip_prefix("application/vnd.oasis.opendocument.")
+    {
+        return match kind {
+            "spreadsheet" => Format::Spreadsheet,
+            _ => Format::Odf,
+        };
+    }
+
+    let names: Vec<&str> = archive.file_names().collect();
+    if names.contains(&"[Content_Types].xml") {
+        if names.contains(&"word/document.xml") || names.contains(&"ppt/presentation.xml") {
+            return Format::Ooxml;
+        }
+        if names.contains(&"xl/workbook.xml") || names.contains(&"xl/workbook.bin") {
+            return Format::Spreadsheet;
+        }
+    }
+    Format::Zip
+}
+
+/// Picks the extractor for a Compound File Binary document from its root streams.
+fn sniff_cfb(data: &[u8]) -> Format {
+    let comp = match CompoundFile::open(Cursor::new(data)) {
+        Ok(c) => c,
+        Err(_) => return Format::LegacyOffice,
+    };
+    if comp.is_stream("/Workbook") || comp.is_stream("/Book") {
+        return Format::Spreadsheet;
+    }
+    if comp.is_stream("/__properties_version1.0") {
+        return Format::Msg;
+    }
+    if [ole::OLE10_NATIVE, ole::PACKAGE, ole::CONTENTS]
+        .iter()
+        .any(|stream| comp.is_stream(stream))
+    {
+        return Format::OleObject;
+    }
+    Format::LegacyOffice
+}
+
+/// Text has no NUL bytes and is valid UTF-8 or, for legacy 8-bit encodings, mostly
+/// printable. UTF-16, with or without a byte order mark, also counts.
+fn looks_like_text(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(HEAD_LEN)];
+    if head.is_empty() {
+        return false;
+    }
+    if head.starts_with(b"\xFF\xFE") || head.starts_with(b"\xFE\xFF") {
+        return true;
+    }
+    if text_encoding::utf16_without_bom(head).is_some() {
+        return true;
+    }
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => return true,
+        // A multi-byte character cut off at the end of the head.
+        Err(e) if e.error_len().is_none() => return true,
+        Err(_) => (),
+    }
+    let printable = head
+        .iter()
+        .filter(|b| b.is_ascii_graphic() || b.is_ascii_whitespace() || **b >= 0x80)
+        .count();
+    printable * 100 >= head.len() * 95
+}
+
+fn sniff_text(data: &[u8]) -> Format {
+    let start = String::from_utf8_lossy(&data[..data.len().min(64)]).to_lowercase();
+    let start = start.trim_start_matches('\u{FEFF}').trim_start();
+    if MARKUP_STARTS.iter().any(|m| start.starts_with(m)) {
+        return Format::Markup;
+    }
+    let mut lines = data.split(|b| *b == b'\n');
+    let first = lines.next().unwrap_or_default();
+    let second = lines.next().unwrap_or_default();
+    if first.starts_with(b"From ") && header_name(second).is_some() {
+        return Format::Mbox;
+    }
+    match header_name(first) {
+        Some(name) if name.starts_with("x-") || EMAIL_HEADERS.contains(&name.as_str()) => {
+            Format::Email
+        }
+        _ => Format::Text,
+    }
+}
+
+/// Returns the lower-cased field name of a `Name: value` header line.
+fn header_name(line: &[u8]) -> Option<String> {
+    let colon = line.iter().position(|b| *b == b':')?;
+    let name = &line[..colon];
+    if name.is_empty() || !name.iter().all(|b| b.is_ascii_graphic()) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(name).to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    use super::{resolve, sniff, TAR_MAGIC_OFFSET};
+    use crate::file_handler::Format;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn tar_header() -> Vec<u8> {
+        let mut header = vec![0; 512];
+        header[TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 6].copy_from_slice(b"ustar\0");
+        header
+    }
+
+    #[test]
+    fn signatures() {
+        assert_eq!(sniff(b"{\\rtf1 text}"), Some(Format::Rtf));
+        assert_eq!(sniff(b"\xFFWPC\x10\x00\x00\x00"), Some(Format::WordPerfect));
+        assert_eq!(sniff(&tar_header()), Some(Format::Tar));
+        assert_eq!(sniff(&gzip(b"SSN 123-45-6789")), Some(Format::Gzip));
+        assert_eq!(sniff(&gzip(&tar_header())), Some(Format::TarGz));
+        assert_eq!(sniff(b"BZh91AY&SY"), Some(Format::Bzip2));
+        assert_eq!(sniff(b"\xFD7zXZ\x00\x00\x04"), Some(Format::Xz));
+        assert_eq!(sniff(b"\x28\xB5\x2F\xFD\x00"), Some(Format::Zstd));
+    }
+
+    #[test]
+    fn pdf_header() {
+        assert_eq!(sniff(b"%PDF-1.7\n"), Some(Format::Pdf));
+        assert_eq!(sniff(b"\xEF\xBB\xBF \r\n%PDF-1.4\n"), Some(Format::Pdf));
+        assert_eq!(sniff(b"see the %PDF-1.4 header"), Some(Format::Text));
+        assert_eq!(sniff(b"\xEF\xBB\xBF"), Some(Format::Text));
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(sniff(b"SSN 123-45-6789"), Some(Format::Text));
+        assert_eq!(sniff(b"BZh is not bzip2"), Some(Format::Text));
+        assert_eq!(sniff(b"<!DOCTYPE html><p>x</p>"), Some(Format::Markup));
+        assert_eq!(
+            sniff(b"\xEF\xBB\xBF  <?xml version=\"1.0\"?>"),
+            Some(Format::Markup)
+        );
+        assert_eq!(
+            sniff(b"From jane@example.com Mon Jan 1\nReturn-Path: <jane@example.com>\n"),
+            Some(Format::Mbox)
+        );
+        assert_eq!(sniff(b"Received: from mx\n"), Some(Format::Email));
+        assert_eq!(sniff(b"X-Mailer: test\n"), Some(Format::Email));
+        assert_eq!(sniff(b"Subject: not enough\n"), Some(Format::Text));
+        assert_eq!(sniff(b"\xFF\xFEa\x00b\x00"), Some(Format::Text));
+        assert_eq!(sniff(b"caf\xE9 cr\xE8me"), Some(Format::Text));
+    }
+
+    #[test]
+    fn binary_and_truncated() {
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"\x00\x01\x02\x03\xFF"), None);
+        assert_eq!(sniff(b"PK\x03\x04"), Some(Format::Zip));
+        assert_eq!(sniff(b"PK\x05\x06"), Some(Format::Zip));
+        assert_eq!(
+            sniff(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1"),
+            Some(Format::LegacyOffice)
+        );
+        assert_eq!(sniff(b"\x1F\x8B"), Some(Format::Gzip));
+        assert_eq!(sniff(&gzip(&tar_header())[..12]), Some(Format::Gzip));
+    }
+
+    #[test]
+    fn name_and_content() {
+        assert_eq!(resolve(None, None), None);
+        assert_eq!(
+            resolve(Some(Format::Delimited), Some(Format::Text)),
+            Some((Format::Delimited, None))
+        );
+        assert_eq!(resolve(None, Some(Format::Pdf)), Some((Format::Pdf, None)));
+        let (format, note) = resolve(Some(Format::Pdf), Some(Format::Zip)).unwrap();
+        assert_eq!(format, Format::Zip);
+        assert!(note.is_some());
+    }
+}
@@ -0,0 +1,87 @@
+use calamine::{open_workbook_auto_from_rs, Data, Reader, SheetType, SheetVisible};
+use std::io::Cursor;
+
+use crate::file_handler::Segment;
+
+/// Extracts every non-empty cell of an xlsx, xlsm, xlsb, xls or ods workbook as its own
+/// segment, located as `Sheet1!B7`.
+///
+/// Shared strings and cached formula results are resolved to the value shown in the cell.
+/// Hidden and very hidden sheets are read like any other and flagged in the location; hidden
+/// rows and columns are stored as ordinary cells, so they are always included.
+pub fn extract(data: &[u8]) -> Option<Vec<Segment>> {
+    let mut workbook = match open_workbook_auto_from_rs(Cursor::new(data)) {
+        Ok(w) => w,
+        Err(e) => {
+            log::error!("failed to open workbook: {}", e);
+            return None;
+        }
+    };
+
+    let sheets = workbook.sheets_metadata().to_vec();
+    let mut segments = Vec::new();
+    for sheet in sheets {
+        if sheet.typ == SheetType::ChartSheet {
+            continue;
+        }
+        let range = match workbook.worksheet_range(&sheet.name) {
+            Ok(r) => r,
+            Err(e) => {
+                log::error!("failed to read sheet {}: {}", sheet.name, e);
+                continue;
+            }
+        };
+        let (row_start, col_start) = match range.start() {
+            Some(s) => s,
+            None => continue,
+        };
+        let sheet_name = quote_sheet_name(&sheet.name);
+        let visibility = match sheet.visible {
+            SheetVisible::Visible => "",
+            SheetVisible::Hidden => " (hidden sheet)",
+            SheetVisible::VeryHidden => " (very hidden sheet)",
+        };
+
+        for (row, col, cell) in range.used_cells() {
+            if matches!(cell, Data::Empty | Data::Error(_)) {
+                continue;
+            }
+            let row = row_start as usize + row + 1;
+            let col = col_start as usize + col;
+            segments.push(Segment {
+                location: Some(format!(
+                    "{}!{}{}{}",
+                    sheet_name,
+                    column_name(col),
+                    row,
+                    visibility
+                )),
+                text: cell.to_string(),
+            });
+        }
+    }
+
+    Some(segments)
+}
+
+/// Quotes sheet names the way Excel does in references, e.g. `'Q1 Payroll'!A1`.
+fn quote_sheet_name(name: &str) -> String {
+    if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return name.to_string();
+    }
+    format!("'{}'", name.replace('\'', "''"))
+}
+
+/// Converts a zero based column index to its letter name (0 -> A, 27 -> AB).
+fn column_name(mut col: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (col % 26) as u8);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
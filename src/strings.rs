@@ -0,0 +1,61 @@
+use crate::file_handler::Segment;
+
+/// Note attached to documents searched through their strings, whose findings may come
+/// from unrelated bytes that happen to be printable.
+pub const LOW_CONFIDENCE: &str = "low confidence: printable strings of an unrecognised file";
+
+/// Extracts runs of at least `min_length` printable characters, like `strings` and
+/// `strings -el`, as an `ASCII strings` and a `UTF-16LE strings` segment. Each run is put
+/// on its own line.
+pub fn extract(data: &[u8], min_length: usize) -> Vec<Segment> {
+    let min_length = min_length.max(1);
+    let mut segments = Vec::new();
+
+    let ascii = runs(data.iter().copied(), min_length);
+    if !ascii.is_empty() {
+        segments.push(Segment {
+            location: Some("ASCII strings".to_string()),
+            text: ascii,
+        });
+    }
+
+    // UTF-16LE text can start on either byte, so both alignments are read.
+    let mut utf16 = String::new();
+    for offset in 0..2 {
+        let units = data
+            .get(offset..)
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .map(|pair| match pair[1] {
+                0 => pair[0],
+                _ => 0,
+            });
+        utf16.push_str(&runs(units, min_length));
+    }
+    if !utf16.is_empty() {
+        segments.push(Segment {
+            location: Some("UTF-16LE strings".to_string()),
+            text: utf16,
+        });
+    }
+
+    segments
+}
+
+/// Collects the runs of printable ASCII characters, tabs included, that are long enough.
+fn runs<I: Iterator<Item = u8>>(bytes: I, min_length: usize) -> String {
+    let mut text = String::new();
+    let mut run = String::new();
+    for byte in bytes.chain(std::iter::once(0)) {
+        if byte.is_ascii_graphic() || byte == b' ' || byte == b'\t' {
+            run.push(byte as char);
+            continue;
+        }
+        if run.len() >= min_length {
+            text.push_str(&run);
+            text.push('\n');
+        }
+        run.clear();
+    }
+    text
+}
@@ -0,0 +1,241 @@
+use crate::file_handler::{ExtractSettings, Format, Segment};
+use crate::patterns::Patterns;
+
+/// Segments of a parsed JSON, YAML or TOML document.
+pub struct Extracted {
+    /// Scalar values, located by their path such as `$.customers[42].ssn`, and key names
+    /// when those are scanned too.
+    pub segments: Vec<Segment>,
+    /// Values under a key that matches a key rule, with the index of that rule. These are
+    /// reported as findings without being searched.
+    pub keyed_values: Vec<(usize, Segment)>,
+}
+
+/// A parsed document, independent of the syntax it was written in.
+enum Node {
+    Scalar(String),
+    List(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+/// Parses a JSON, JSON Lines, YAML or TOML document into its values. Returns `None` when
+/// the text doesn't parse, so it can be scanned as plain text instead. JSON Lines that
+/// don't parse are kept as text, located as `line 7`.
+pub fn extract(
+    text: &str,
+    format: Format,
+    settings: &ExtractSettings,
+    patterns: &Patterns,
+) -> Option<Extracted> {
+    let mut unparsed = Vec::new();
+    let documents: Vec<(String, Node)> = match format {
+        Format::Json => vec![("$".to_string(), parse_json(text)?)],
+        Format::JsonLines => {
+            let mut documents = Vec::new();
+            for (i, line) in text.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<serde_json::Value>(line) {
+                    Ok(v) => documents.push((format!("line {}, $", i + 1), Node::from(v))),
+                    Err(_) => unparsed.push(Segment {
+                        location: Some(format!("line {}", i + 1)),
+                        text: line.to_string(),
+                    }),
+                }
+            }
+            if documents.is_empty() && !unparsed.is_empty() {
+                log::warn!("failed to parse json lines: no line is json");
+                return None;
+            }
+            if !unparsed.is_empty() {
+                log::warn!(
+                    "scanning {} lines that are not json as text",
+                    unparsed.len()
+                );
+            }
+            documents
+        }
+        Format::Yaml => {
+            let mut documents = Vec::new();
+            for document in serde_yaml::Deserializer::from_str(text) {
+                let value: serde_yaml::Value = match serde::Deserialize::deserialize(document) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        log::warn!("failed to parse yaml: {}", e);
+                        return None;
+                    }
+                };
+                documents.push(Node::from(value));
+            }
+            match documents.len() {
+                1 => documents
+                    .into_iter()
+                    .map(|d| ("$".to_string(), d))
+                    .collect(),
+                _ => documents
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, d)| (format!("document {}, $", i + 1), d))
+                    .collect(),
+            }
+        }
+        Format::Toml => match text.parse::<toml::Table>() {
+            Ok(table) => vec![("$".to_string(), Node::from(toml::Value::Table(table)))],
+            Err(e) => {
+                log::warn!("failed to parse toml: {}", e);
+                return None;
+            }
+        },
+        _ => return None,
+    };
+
+    let mut extracted = Extracted {
+        segments: unparsed,
+        keyed_values: Vec::new(),
+    };
+    for (root, node) in documents {
+        walk(&node, root, None, settings, patterns, &mut extracted);
+    }
+    Some(extracted)
+}
+
+/// Records the scalars below `node`. `keyed` holds the key rule matched by a key that has
+/// been passed, so every value in that subtree is reported under it.
+fn walk(
+    node: &Node,
+    path: String,
+    keyed: Option<usize>,
+    settings: &ExtractSettings,
+    patterns: &Patterns,
+    extracted: &mut Extracted,
+) {
+    match node {
+        Node::Scalar(value) => {
+            let segment = Segment {
+                location: Some(path),
+                text: value.clone(),
+            };
+            match keyed {
+                Some(rule) if !value.is_empty() => extracted.keyed_values.push((rule, segment)),
+                _ => extracted.segments.push(segment),
+            }
+        }
+        Node::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let path = format!("{}[{}]", path, i);
+                walk(item, path, keyed, settings, patterns, extracted);
+            }
+        }
+        Node::Map(entries) => {
+            for (key, value) in entries {
+                let path = child_path(&path, key);
+                if settings.structured_keys {
+                    extracted.segments.push(Segment {
+                        location: Some(format!("{} (key)", path)),
+                        text: key.clone(),
+                    });
+                }
+                let keyed = keyed.or_else(|| patterns.key_rule(key));
+                walk(value, path, keyed, settings, patterns, extracted);
+            }
+        }
+    }
+}
+
+/// Appends a key to a path, as `$.name` or, when it isn't a plain identifier, `$['a key']`.
+fn child_path(path: &str, key: &str) -> String {
+    let plain = key.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    match plain {
+        true => format!("{}.{}", path, key),
+        false => format!("{}['{}']", path, key.replace('\'', "\\'")),
+    }
+}
+
+fn parse_json(text: &str) -> Option<Node> {
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(v) => Some(Node::from(v)),
+        Err(e) => {
+            log::warn!("failed to parse json: {}", e);
+            None
+        }
+    }
+}
+
+impl From<serde_json::Value> for Node {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value;
+        match value {
+            Value::Null => Node::Scalar(String::new()),
+            Value::Bool(b) => Node::Scalar(b.to_string()),
+            Value::Number(n) => Node::Scalar(n.to_string()),
+            Value::String(s) => Node::Scalar(s),
+            Value::Array(items) => Node::List(items.into_iter().map(Node::from).collect()),
+            Value::Object(entries) => Node::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, Node::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<serde_yaml::Value> for Node {
+    fn from(value: serde_yaml::Value) -> Self {
+        use serde_yaml::Value;
+        match value {
+            Value::Null => Node::Scalar(String::new()),
+            Value::Bool(b) => Node::Scalar(b.to_string()),
+            Value::Number(n) => Node::Scalar(n.to_string()),
+            Value::String(s) => Node::Scalar(s),
+            Value::Sequence(items) => Node::List(items.into_iter().map(Node::from).collect()),
+            Value::Mapping(entries) => Node::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (yaml_key(k), Node::from(v)))
+                    .collect(),
+            ),
+            Value::Tagged(tagged) => Node::from(tagged.value),
+        }
+    }
+}
+
+/// YAML keys can be any value. Scalars are used as written and anything else as its YAML.
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_) => {
+            serde_yaml::to_string(&key)
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        }
+        key => match Node::from(key) {
+            Node::Scalar(s) => s,
+            _ => String::new(),
+        },
+    }
+}
+
+impl From<toml::Value> for Node {
+    fn from(value: toml::Value) -> Self {
+        use toml::Value;
+        match value {
+            Value::String(s) => Node::Scalar(s),
+            Value::Integer(i) => Node::Scalar(i.to_string()),
+            Value::Float(f) => Node::Scalar(f.to_string()),
+            Value::Boolean(b) => Node::Scalar(b.to_string()),
+            Value::Datetime(d) => Node::Scalar(d.to_string()),
+            Value::Array(items) => Node::List(items.into_iter().map(Node::from).collect()),
+            Value::Table(entries) => Node::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, Node::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+/// Number of leading bytes inspected when looking for UTF-16 without a byte order mark.
+const UTF16_PROBE_LEN: usize = 4096;
+
+/// Decodes text of unknown encoding, returning the text and the encoding it was read as.
+///
+/// A byte order mark is trusted first, then UTF-16 is recognised by its pattern of zero
+/// bytes, and valid UTF-8 is taken as is. Anything else is decoded with the single or
+/// multi-byte legacy encoding that best fits the byte statistics, usually Windows-1252.
+pub fn decode(data: &[u8]) -> (String, &'static Encoding) {
+    let (encoding, body) = match Encoding::for_bom(data) {
+        Some((encoding, bom_len)) => (encoding, &data[bom_len..]),
+        None => (detect(data), data),
+    };
+    let (text, _) = encoding.decode_without_bom_handling(body);
+    (text.into_owned(), encoding)
+}
+
+fn detect(data: &[u8]) -> &'static Encoding {
+    if let Some(encoding) = utf16_without_bom(data) {
+        return encoding;
+    }
+    if std::str::from_utf8(data).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(data, true);
+    detector.guess(None, true)
+}
+
+/// Mostly-ASCII UTF-16 text has a zero in nearly every other byte, on the odd positions
+/// for little endian and the even ones for big endian.
+pub fn utf16_without_bom(data: &[u8]) -> Option<&'static Encoding> {
+    let head = &data[..data.len().min(UTF16_PROBE_LEN)];
+    let pairs = head.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let even_zeros = head.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = head.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if odd_zeros * 10 >= pairs * 7 && even_zeros * 20 <= pairs {
+        return Some(UTF_16LE);
+    }
+    if even_zeros * 10 >= pairs * 7 && odd_zeros * 20 <= pairs {
+        return Some(UTF_16BE);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251, WINDOWS_1252};
+
+    use super::{decode, utf16_without_bom};
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| match big_endian {
+                true => unit.to_be_bytes(),
+                false => unit.to_le_bytes(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn byte_order_marks() {
+        assert_eq!(
+            decode(b"\xEF\xBB\xBFcaf\xC3\xA9"),
+            ("caf\u{e9}".to_string(), UTF_8)
+        );
+        let mut le = b"\xFF\xFE".to_vec();
+        le.extend(utf16("SSN 123-45-6789", false));
+        assert_eq!(decode(&le), ("SSN 123-45-6789".to_string(), UTF_16LE));
+        let mut be = b"\xFE\xFF".to_vec();
+        be.extend(utf16("SSN 123-45-6789", true));
+        assert_eq!(decode(&be), ("SSN 123-45-6789".to_string(), UTF_16BE));
+    }
+
+    #[test]
+    fn utf16_by_zero_bytes() {
+        let le = utf16("SSN 123-45-6789", false);
+        assert_eq!(utf16_without_bom(&le), Some(UTF_16LE));
+        assert_eq!(decode(&le), ("SSN 123-45-6789".to_string(), UTF_16LE));
+        let be = utf16("SSN 123-45-6789", true);
+        assert_eq!(utf16_without_bom(&be), Some(UTF_16BE));
+        assert_eq!(utf16_without_bom(b"SSN 123-45-6789"), None);
+        assert_eq!(utf16_without_bom(b"a\x00"), None);
+    }
+
+    #[test]
+    fn legacy_encodings() {
+        assert_eq!(decode("caf\u{e9}".as_bytes()).1, UTF_8);
+
+        let french = "Le caf\u{e9} de la soci\u{e9}t\u{e9} g\u{e9}n\u{e9}rale est \u{e0} c\u{f4}t\u{e9} de l'\u{e9}glise.";
+        let (encoded, _, _) = WINDOWS_1252.encode(french);
+        assert_eq!(decode(&encoded), (french.to_string(), WINDOWS_1252));
+
+        let russian = "Номер паспорта и дата рождения клиента указаны в договоре.";
+        let (encoded, _, _) = WINDOWS_1251.encode(russian);
+        assert_eq!(decode(&encoded), (russian.to_string(), WINDOWS_1251));
+
+        let japanese = "顧客の電話番号と住所は契約書に記載されています。";
+        let (encoded, _, _) = SHIFT_JIS.encode(japanese);
+        assert_eq!(decode(&encoded), (japanese.to_string(), SHIFT_JIS));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(decode(b""), (String::new(), UTF_8));
+        assert_eq!(decode(b"\xFF\xFEa").0, "\u{FFFD}");
+        assert_eq!(decode(b"\xEF\xBB\xBF\xC3").0, "\u{FFFD}");
+        assert!(!decode(b"\xFF").0.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A check run on each match of a rule's pattern. Matches that fail it are not reported,
+/// only counted. Separators such as spaces and dashes are ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Validator {
+    /// Payment card numbers, with the Luhn checksum.
+    Luhn,
+    /// US Social Security numbers, with the area, group and serial rules of the SSA.
+    Ssn,
+    /// US Individual Taxpayer Identification numbers, with the group ranges of the IRS.
+    Itin,
+    /// International bank account numbers, with the ISO 13616 mod-97 checksum.
+    Iban,
+    /// US bank routing numbers, with the ABA checksum.
+    Aba,
+}
+
+impl fmt::Display for Validator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Validator::Luhn => "luhn",
+            Validator::Ssn => "ssn",
+            Validator::Itin => "itin",
+            Validator::Iban => "iban",
+            Validator::Aba => "aba",
+        };
+        f.pad(name)
+    }
+}
+
+impl Validator {
+    /// Returns whether a match is a valid number of this kind.
+    pub fn validate(&self, candidate: &str) -> bool {
+        match self {
+            Validator::Luhn => luhn(&digits(candidate)),
+            Validator::Ssn => ssn(&digits(candidate)),
+            Validator::Itin => itin(&digits(candidate)),
+            Validator::Iban => iban(candidate),
+            Validator::Aba => aba(&digits(candidate)),
+        }
+    }
+}
+
+/// The digits of a candidate, or nothing if it has anything other than digits and
+/// separators.
+fn digits(candidate: &str) -> Vec<u32> {
+    let mut digits = Vec::with_capacity(candidate.len());
+    for c in candidate.chars() {
+        match c.to_digit(10) {
+            Some(d) => digits.push(d),
+            None if is_separator(c) => (),
+            None => return Vec::new(),
+        }
+    }
+    digits
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, ' ' | '-' | '.' | '\u{a0}')
+}
+
+/// Card numbers are 12 to 19 digits whose Luhn sum is a multiple of 10.
+fn luhn(digits: &[u32]) -> bool {
+    if !(12..=19).contains(&digits.len()) || digits.iter().all(|d| *d == 0) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| match i % 2 {
+            0 => *d,
+            _ if *d > 4 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// No SSN has area 000, 666 or 900-999, group 00 or serial 0000. `078-05-1120` was
+/// printed on sample cards and voided.
+fn ssn(digits: &[u32]) -> bool {
+    if digits.len() != 9 {
+        return false;
+    }
+    let number = |range: std::ops::Range<usize>| digits[range].iter().fold(0, |n, d| n * 10 + d);
+    let (area, group, serial) = (number(0..3), number(3..5), number(5..9));
+    if area == 0 || area == 666 || area >= 900 || group == 0 || serial == 0 {
+        return false;
+    }
+    (area, group, serial) != (78, 5, 1120)
+}
+
+/// ITINs start with 9 and have a group of 50-65, 70-88, 90-92 or 94-99.
+/// `987-65-4320` to `987-65-4329` are set aside for advertisements.
+fn itin(digits: &[u32]) -> bool {
+    if digits.len() != 9 || digits[0] != 9 {
+        return false;
+    }
+    let number = |range: std::ops::Range<usize>| digits[range].iter().fold(0, |n, d| n * 10 + d);
+    let (area, group, serial) = (number(0..3), number(3..5), number(5..9));
+    if !matches!(group, 50..=65 | 70..=88 | 90..=92 | 94..=99) {
+        return false;
+    }
+    !(area == 987 && group == 65 && (4320..=4329).contains(&serial))
+}
+
+/// Two letters for the country, two check digits and up to 30 letters and digits. Moving
+/// the first four characters to the end and reading letters as 10 to 35 gives a number
+/// that leaves 1 when divided by 97.
+fn iban(candidate: &str) -> bool {
+    let characters: Vec<char> = candidate
+        .chars()
+        .filter(|c| !is_separator(*c))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if !(15..=34).contains(&characters.len())
+        || !characters[..2].iter().all(char::is_ascii_uppercase)
+        || !characters[2..4].iter().all(char::is_ascii_digit)
+    {
+        return false;
+    }
+    let mut remainder = 0;
+    for c in characters[4..].iter().chain(&characters[..4]) {
+        remainder = match c.to_digit(36) {
+            Some(d) if d < 10 => (remainder * 10 + d) % 97,
+            Some(d) => (remainder * 100 + d) % 97,
+            None => return false,
+        };
+    }
+    remainder == 1
+}
+
+/// Routing numbers are 9 digits weighted 3, 7, 1 whose sum is a multiple of 10.
+fn aba(digits: &[u32]) -> bool {
+    if digits.len() != 9 || digits.iter().all(|d| *d == 0) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .zip([3, 7, 1].iter().cycle())
+        .map(|(d, weight)| d * weight)
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Validator;
+
+    #[test]
+    fn luhn() {
+        assert!(Validator::Luhn.validate("4111 1111 1111 1111"));
+        assert!(Validator::Luhn.validate("4111-1111-1111-1111"));
+        assert!(Validator::Luhn.validate("378282246310005"));
+        assert!(!Validator::Luhn.validate("4111 1111 1111 1112"));
+        assert!(!Validator::Luhn.validate("0000 0000 0000 0000"));
+        assert!(!Validator::Luhn.validate("4111 1111 111"));
+        assert!(!Validator::Luhn.validate("4111x1111x1111x1111"));
+    }
+
+    #[test]
+    fn ssn() {
+        assert!(Validator::Ssn.validate("123-45-6789"));
+        assert!(Validator::Ssn.validate("123456789"));
+        assert!(!Validator::Ssn.validate("000-12-3456"));
+        assert!(!Validator::Ssn.validate("666-12-3456"));
+        assert!(!Validator::Ssn.validate("987-65-4321"));
+        assert!(!Validator::Ssn.validate("123-00-4567"));
+        assert!(!Validator::Ssn.validate("123-45-0000"));
+        assert!(!Validator::Ssn.validate("078-05-1120"));
+        assert!(!Validator::Ssn.validate("123-45-678"));
+    }
+
+    #[test]
+    fn itin() {
+        assert!(Validator::Itin.validate("912-70-1234"));
+        assert!(Validator::Itin.validate("987-65-4330"));
+        assert!(!Validator::Itin.validate("987-65-4321"));
+        assert!(!Validator::Itin.validate("987-65-4329"));
+        assert!(!Validator::Itin.validate("912-40-1234"));
+        assert!(!Validator::Itin.validate("912-93-1234"));
+        assert!(!Validator::Itin.validate("812-70-1234"));
+    }
+
+    #[test]
+    fn iban() {
+        assert!(Validator::Iban.validate("GB82 WEST 1234 5698 7654 32"));
+        assert!(Validator::Iban.validate("gb82west12345698765432"));
+        assert!(Validator::Iban.validate("DE89 3704 0044 0532 0130 00"));
+        assert!(!Validator::Iban.validate("GB83 WEST 1234 5698 7654 32"));
+        assert!(!Validator::Iban.validate("GB82 WEST 1234"));
+        assert!(!Validator::Iban.validate("1282 WEST 1234 5698 7654 32"));
+        assert!(!Validator::Iban.validate("GB82 WEST 1234 5698 7654 3!"));
+    }
+
+    #[test]
+    fn aba() {
+        assert!(Validator::Aba.validate("021000021"));
+        assert!(Validator::Aba.validate("011000015"));
+        assert!(!Validator::Aba.validate("021000022"));
+        assert!(!Validator::Aba.validate("000000000"));
+        assert!(!Validator::Aba.validate("02100002"));
+    }
+}
@@ -0,0 +1,206 @@
+/// Every WordPerfect 5.x and later file opens with `0xFF "WPC"`.
+const WPC_MAGIC: &[u8; 4] = b"\xFFWPC";
+const WP_DOCUMENT: u8 = 0x0A;
+
+/// Total sizes (opening and closing code included) of the WordPerfect 5.x fixed-length
+/// functions 0xC0-0xCF.
+const WP5_FIXED_SIZES: [usize; 16] = [4, 9, 11, 3, 3, 5, 6, 7, 4, 5, 5, 6, 8, 10, 10, 12];
+
+/// Total sizes of the WordPerfect 6.x fixed-length functions 0xF0-0xFE.
+const WP6_FIXED_SIZES: [usize; 15] = [4, 5, 3, 3, 5, 5, 6, 6, 8, 8, 10, 10, 12, 12, 14];
+
+/// WordPerfect 6.x stores the most common accented letters as single bytes 0x01-0x1F.
+const WP6_EXTENDED_INTERNATIONAL: [char; 31] = [
+    'å', 'Å', 'æ', 'Æ', 'ä', 'Ä', 'á', 'à', 'â', 'ã', 'Ã', 'ç', 'Ç', 'ë', 'é', 'É', 'è', 'ê', 'í',
+    'ñ', 'Ñ', 'ø', 'Ø', 'õ', 'Õ', 'ö', 'Ö', 'ü', 'Ü', 'ú', 'ù',
+];
+
+/// Extracts the document text of a WordPerfect 5.x or 6.x+ file.
+///
+/// The text area is a stream of ASCII characters interleaved with function packets. Packets
+/// are skipped by their declared length, hard returns become newlines and soft returns
+/// become spaces, so no formatting code ends up between the characters of a word.
+pub fn extract(data: &[u8]) -> Option<String> {
+    if data.len() < 16 || &data[0..4] != WPC_MAGIC {
+        log::error!("not a WordPerfect 5.x or later document");
+        return None;
+    }
+    if data[9] != WP_DOCUMENT {
+        return None;
+    }
+    if u16::from_le_bytes([data[12], data[13]]) != 0 {
+        log::error!("WordPerfect document is password protected");
+        return None;
+    }
+    let start = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let body = data.get(start..).unwrap_or_default();
+
+    match data[10] {
+        0 => Some(wp5_text(body)),
+        _ => Some(wp6_text(body)),
+    }
+}
+
+fn wp5_text(body: &[u8]) -> String {
+    let mut text = String::new();
+    let mut pos = 0;
+    while pos < body.len() {
+        let code = body[pos];
+        match code {
+            0x0A..=0x0C => text.push('\n'),
+            0x0D => text.push(' '),
+            0x20..=0x7F => text.push(code as char),
+            0xA0 => text.push(' '),
+            0xA9..=0xAB => text.push('-'),
+            0xC0..=0xCF => {
+                let size = fixed_size(body, pos, WP5_FIXED_SIZES[(code - 0xC0) as usize]);
+                match code {
+                    0xC0 => text.push(extended_char(body, pos)),
+                    0xC1 => text.push('\t'),
+                    _ => (),
+                }
+                pos += size;
+                continue;
+            }
+            0xD0..=0xFF => {
+                // Variable-length function: code, subgroup, u16 length of the rest.
+                let len = read_u16(body, pos + 2) as usize;
+                pos += 4 + len;
+                continue;
+            }
+            _ => (),
+        }
+        pos += 1;
+    }
+    text
+}
+
+fn wp6_text(body: &[u8]) -> String {
+    let mut text = String::new();
+    let mut pos = 0;
+    while pos < body.len() {
+        let code = body[pos];
+        match code {
+            0x01..=0x1F => text.push(WP6_EXTENDED_INTERNATIONAL[(code - 1) as usize]),
+            0x20..=0x7F => text.push(code as char),
+            0x80 | 0x81 => text.push(' '),
+            0x84 => text.push('-'),
+            0x87 | 0xCC..=0xCF => text.push('\n'),
+            0x88..=0xCB => text.push(' '),
+            0xD0..=0xEF => {
+                // Variable-length function: code, subgroup, u16 total size.
+                let size = read_u16(body, pos + 2) as usize;
+                pos += size.max(1);
+                continue;
+            }
+            0xF0..=0xFE => {
+                let size = fixed_size(body, pos, WP6_FIXED_SIZES[(code - 0xF0) as usize]);
+                if code == 0xF0 {
+                    text.push(extended_char(body, pos));
+                }
+                pos += size;
+                continue;
+            }
+            _ => (),
+        }
+        pos += 1;
+    }
+    text
+}
+
+/// Fixed-length functions repeat their code as the last byte. When that doesn't hold for
+/// the expected size, the closing code is searched for instead so one unknown packet can't
+/// desynchronise the rest of the document.
+fn fixed_size(body: &[u8], pos: usize, expected: usize) -> usize {
+    let code = body[pos];
+    if body.get(pos + expected - 1) == Some(&code) {
+        return expected;
+    }
+    match body[pos + 1..].iter().take(16).position(|b| *b == code) {
+        Some(offset) => offset + 2,
+        None => expected,
+    }
+}
+
+/// Decodes an extended character packet: code, character, character set, code.
+fn extended_char(body: &[u8], pos: usize) -> char {
+    let character = body.get(pos + 1).copied().unwrap_or_default();
+    let charset = body.get(pos + 2).copied().unwrap_or_default();
+    match charset {
+        0 if (0x20..0x7F).contains(&character) => character as char,
+        _ => char::REPLACEMENT_CHARACTER,
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    match data.get(offset..offset + 2) {
+        Some(b) => u16::from_le_bytes([b[0], b[1]]),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract;
+
+    /// A document with a 16 byte prefix and `body` as its text area.
+    fn document(major_version: u8, body: &[u8]) -> Vec<u8> {
+        let mut data = b"\xFFWPC".to_vec();
+        data.extend(16u32.to_le_bytes());
+        data.extend([1, 0x0A, major_version, 0, 0, 0, 0, 0]);
+        data.extend(body);
+        data
+    }
+
+    fn text(major_version: u8, body: &[u8]) -> String {
+        extract(&document(major_version, body)).unwrap()
+    }
+
+    #[test]
+    fn header() {
+        assert!(extract(b"\xFFWPC").is_none());
+        assert!(extract(b"{\\rtf1 not a WordPerfect file}").is_none());
+        let mut encrypted = document(0, b"text");
+        encrypted[12] = 1;
+        assert!(extract(&encrypted).is_none());
+        let mut macro_file = document(0, b"text");
+        macro_file[9] = 0x01;
+        assert!(extract(&macro_file).is_none());
+    }
+
+    #[test]
+    fn wp5() {
+        assert_eq!(
+            text(0, b"SSN\x0D123-45-6789\x0Adone"),
+            "SSN 123-45-6789\ndone"
+        );
+        // A variable-length packet inside a number.
+        assert_eq!(
+            text(0, b"123-45\xD0\x01\x04\x00abc\xD0-6789"),
+            "123-45-6789"
+        );
+        // A fixed-length packet, and one that doesn't close where expected.
+        assert_eq!(text(0, b"12\xC3\x01\xC33"), "123");
+        assert_eq!(text(0, b"12\xC3\x01\x02\xC33"), "123");
+        assert_eq!(text(0, b"a\xC0b\x00\xC0\xC1\x00\x00\x00\xC1c"), "ab\tc");
+    }
+
+    #[test]
+    fn wp6() {
+        assert_eq!(text(2, b"caf\x0F\x80ok\xCCnext"), "caf\u{e9} ok\nnext");
+        assert_eq!(text(2, b"12\xD4\x00\x06\x00\x01\xD43"), "123");
+        assert_eq!(text(2, b"12\xF2\x01\xF23"), "123");
+    }
+
+    #[test]
+    fn truncated() {
+        let mut past_end = document(0, b"text");
+        past_end[4..8].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(extract(&past_end).unwrap(), "");
+        assert_eq!(text(0, b"ab\xD0"), "ab");
+        assert_eq!(text(0, b"ab\xD0\x01\xFF\xFF"), "ab");
+        assert_eq!(text(0, b"ab\xCF"), "ab");
+        assert_eq!(text(2, b"ab\xD0\x00\x00\x00c"), "abc");
+        assert_eq!(text(2, b"ab\xFE"), "ab");
+    }
+}